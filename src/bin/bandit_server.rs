@@ -0,0 +1,12 @@
+use bandit_rs::Server;
+
+const DEFAULT_ADDR: &str = "127.0.0.1:8000";
+
+// Serve bandit sessions over HTTP, on the address given as first
+// argument (or DEFAULT_ADDR).
+fn main() {
+  let addr = std::env::args().nth(1).unwrap_or_else(|| DEFAULT_ADDR.to_string());
+  let server = Server::bind(&addr).unwrap_or_else(|e| panic!("Cannot bind {}: {}", addr, e));
+  println!("Listening on {}", server.local_addr());
+  server.run();
+}
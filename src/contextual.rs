@@ -0,0 +1,106 @@
+// Contextual bandits: at each round, the problem gives a context
+// to the policy before it chooses a lever, and the rewards depend
+// on this context. The results are the same as for context-free
+// problems, so they can be analysed with the same functions.
+
+use rand::Rng;
+
+mod problems;
+mod policies;
+mod experiments;
+mod linalg;
+
+pub use problems::ContextualBanditInit;
+pub use policies::ContextualPolicyInit;
+use problems::create_contextual_bandit;
+use policies::create_contextual_policy;
+use experiments::ContextualExperiment;
+use crate::experiments::Step;
+use crate::{make_vec_experiment,run_vec_experiment,run_reprod_vec_experiment};
+
+// With record_probabilities, the steps also hold the probabilities
+// of policies which can only estimate them by sampling decisions.
+pub fn run_contextual_experiments(policies : &[ContextualPolicyInit],
+                                  problem : ContextualBanditInit,
+                                  record_probabilities : bool,
+                                  nb_tries : usize,
+                                  len_exp : usize) -> Vec<Vec<Vec<Step>>> {
+
+  let exps = make_vec_experiment(policies,&mut rand::thread_rng(),nb_tries,|policy,rng|
+    make_experiment(policy,&problem,record_probabilities,rng,len_exp)
+  );
+  run_vec_experiment(exps,len_exp)
+}
+
+pub fn run_reprod_contextual_experiments<T> (policies : &[ContextualPolicyInit],
+                                             problem : ContextualBanditInit,
+                                             record_probabilities : bool,
+                                             rng : &mut T,
+                                             nb_tries : usize,
+                                             len_exp : usize) -> Vec<Vec<Vec<Step>>>
+  where T : Rng {
+
+  let exps = make_vec_experiment(policies,rng,nb_tries,|policy,rng|
+    make_experiment(policy,&problem,record_probabilities,rng,len_exp)
+  );
+  run_reprod_vec_experiment(exps,rng,len_exp)
+}
+
+fn make_experiment<T>(policy : &ContextualPolicyInit,
+                      problem : &ContextualBanditInit,
+                      record_probabilities : bool,
+                      rng : &mut T,
+                      len_exp : usize) -> ContextualExperiment
+  where T : Rng {
+
+  ContextualExperiment::new(create_contextual_policy(policy,Some(len_exp)),
+                            create_contextual_bandit(problem,rng))
+    .record_probabilities(record_probabilities)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EstimatorInit,PolicyInit};
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_context_free_runs() {
+        let est = EstimatorInit::SampleAverageInit {nb_levers : 3};
+        let policy = PolicyInit::EGreedyInit {nb_levers : 3, expl_proba : 0.1.into(), est : &est};
+        let problem = ContextualBanditInit::LogisticInit {nb_levers : 3,
+                                                          dim : 4,
+                                                          params : (0.0,1.0)};
+        let results = run_reprod_contextual_experiments(&[ContextualPolicyInit::ContextFreeInit {policy : &policy}],
+                                                        problem,
+                                                        false,
+                                                        &mut StdRng::seed_from_u64(0),
+                                                        5,
+                                                        20);
+        assert_eq!(results[0].len(),5);
+        assert!(results[0].iter().all(|exp| exp.len() == 20));
+        assert!(results[0][0].iter().all(|step| step.reward == 0.0 || step.reward == 1.0));
+    }
+
+    #[test]
+    fn test_linear_policies_learn() {
+        let problem = ContextualBanditInit::LinearInit {nb_levers : 3,
+                                                        dim : 3,
+                                                        params : (0.0,1.0),
+                                                        noise : 0.1};
+        let policies = [ContextualPolicyInit::LinUCBInit {nb_levers : 3, dim : 3,
+                                                          alpha : 0.5, regularization : 1.0},
+                        ContextualPolicyInit::HybridLinUCBInit {nb_levers : 3, dim : 3,
+                                                                alpha : 0.5, regularization : 1.0},
+                        ContextualPolicyInit::LinTSInit {nb_levers : 3, dim : 3,
+                                                         scale : 0.1, regularization : 1.0}];
+        let results = crate::optimal_percentage(
+          run_reprod_contextual_experiments(&policies,problem,false,&mut StdRng::seed_from_u64(0),10,200),
+          10,200);
+        for percentages in results {
+            let end = percentages[150..].iter().sum::<f64>() / 50.0;
+            assert!(end > 0.8, "{}", end);
+        }
+    }
+}
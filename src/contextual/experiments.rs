@@ -0,0 +1,61 @@
+use rand::Rng;
+
+use crate::helper;
+use crate::experiments::{Step,Runnable};
+use crate::contextual::problems::ContextualBandit;
+use crate::contextual::problems::ContextualBanditEnum;
+use crate::contextual::policies::ContextualPolicy;
+use crate::contextual::policies::ContextualPolicyEnum;
+
+#[derive(Clone)]
+pub(crate) struct ContextualExperiment {
+  policy : ContextualPolicyEnum,
+  problem : ContextualBanditEnum,
+  results : Vec<Step>,
+  // Whether to record probabilities the policy can only estimate.
+  record : bool,
+}
+
+impl ContextualExperiment {
+
+  pub(crate) fn new(policy : ContextualPolicyEnum,
+                    problem : ContextualBanditEnum) -> Self {
+    ContextualExperiment {
+      problem,
+      policy,
+      results : Vec::new(),
+      record : false,
+    }
+  }
+
+  pub(crate) fn record_probabilities(mut self, record : bool) -> Self {
+    self.record = record;
+    self
+  }
+}
+
+impl Runnable for ContextualExperiment {
+
+  fn steps<V: Rng>(mut self, steps : usize, rng : &mut V) -> Vec<Step> {
+    for _ in 0..steps {
+      let context = self.problem.context(rng);
+      let probas = if self.record || self.policy.exact_probabilities() {
+        Some(self.policy.probabilities(&context,rng))
+      } else {
+        None
+      };
+      let lever = self.policy.decide(&context,rng);
+      let optimal = self.problem.is_optimal(lever);
+      let reward = self.problem.use_lever(lever,rng);
+      self.policy.update(&context,lever,reward);
+      self.results.push(Step { lever,
+                               optimal,
+                               reward,
+                               propensity : probas.as_ref().map(|probas| probas[lever]),
+                               entropy : probas.as_ref().map(|probas| helper::entropy(probas)),
+                               changepoint : false,
+                               alarm : false, });
+    }
+    self.results
+  }
+}
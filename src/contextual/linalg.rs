@@ -0,0 +1,142 @@
+// Small dense linear algebra, for the ridge regressions of the
+// linear contextual policies. Matrices are stored as rows.
+
+use crate::helper::dot;
+
+pub(crate) type Matrix = Vec<Vec<f64>>;
+
+pub(crate) fn identity(dim : usize, scale : f64) -> Matrix {
+  (0..dim).map(|i| (0..dim).map(|j| if i == j { scale } else { 0.0 })
+                           .collect())
+          .collect()
+}
+
+pub(crate) fn zeros(rows : usize, cols : usize) -> Matrix {
+  vec![vec![0.0;cols];rows]
+}
+
+pub(crate) fn mat_vec(m : &[Vec<f64>], x : &[f64]) -> Vec<f64> {
+  m.iter().map(|row| dot(row,x)).collect()
+}
+
+// Product of the transpose of m with x.
+pub(crate) fn mat_t_vec(m : &[Vec<f64>], x : &[f64]) -> Vec<f64> {
+  let mut result = vec![0.0;m.first().map_or(0,|row| row.len())];
+  for (row,xi) in m.iter().zip(x.iter()) {
+    for (res,mij) in result.iter_mut().zip(row.iter()) {
+      *res += mij*xi;
+    }
+  }
+  result
+}
+
+pub(crate) fn mat_mul(a : &[Vec<f64>], b : &[Vec<f64>]) -> Matrix {
+  a.iter().map(|row| mat_t_vec(b,row)).collect()
+}
+
+pub(crate) fn transpose(m : &[Vec<f64>]) -> Matrix {
+  let cols = m.first().map_or(0,|row| row.len());
+  (0..cols).map(|j| m.iter().map(|row| row[j]).collect())
+           .collect()
+}
+
+// m += scale * x y^T
+pub(crate) fn add_outer(m : &mut Matrix, x : &[f64], y : &[f64], scale : f64) {
+  for (row,xi) in m.iter_mut().zip(x.iter()) {
+    for (mij,yj) in row.iter_mut().zip(y.iter()) {
+      *mij += scale*xi*yj;
+    }
+  }
+}
+
+pub(crate) fn add(a : &mut Matrix, b : &[Vec<f64>], scale : f64) {
+  for (row_a,row_b) in a.iter_mut().zip(b.iter()) {
+    for (aij,bij) in row_a.iter_mut().zip(row_b.iter()) {
+      *aij += scale*bij;
+    }
+  }
+}
+
+// Update the inverse of a symmetric matrix A into the inverse of
+// A + x x^T, with the Sherman-Morrison formula.
+pub(crate) fn sherman_morrison(inv : &mut Matrix, x : &[f64]) {
+  let inv_x = mat_vec(inv,x);
+  let denom = 1.0 + dot(x,&inv_x);
+  add_outer(inv,&inv_x,&inv_x,-1.0/denom);
+}
+
+// Inverse by Gauss-Jordan elimination with partial pivoting.
+// The matrix must be invertible.
+pub(crate) fn inverse(m : &[Vec<f64>]) -> Matrix {
+  let dim = m.len();
+  let mut a : Matrix = m.to_vec();
+  let mut inv = identity(dim,1.0);
+  for col in 0..dim {
+    let pivot = (col..dim).max_by(|i,j| a[*i][col].abs()
+                                                  .partial_cmp(&a[*j][col].abs())
+                                                  .unwrap())
+                          .unwrap();
+    a.swap(col,pivot);
+    inv.swap(col,pivot);
+    let scale = a[col][col];
+    for j in 0..dim {
+      a[col][j] /= scale;
+      inv[col][j] /= scale;
+    }
+    for row in 0..dim {
+      if row != col {
+        let factor = a[row][col];
+        for j in 0..dim {
+          a[row][j] -= factor*a[col][j];
+          inv[row][j] -= factor*inv[col][j];
+        }
+      }
+    }
+  }
+  inv
+}
+
+// Lower triangular L such that L L^T = m, for a symmetric positive
+// definite m.
+pub(crate) fn cholesky(m : &[Vec<f64>]) -> Matrix {
+  let dim = m.len();
+  let mut l = zeros(dim,dim);
+  for i in 0..dim {
+    for j in 0..=i {
+      let sum : f64 = (0..j).map(|k| l[i][k]*l[j][k]).sum();
+      if i == j {
+        l[i][j] = (m[i][i] - sum).max(0.0).sqrt();
+      } else if l[j][j] > 0.0 {
+        l[i][j] = (m[i][j] - sum) / l[j][j];
+      }
+    }
+  }
+  l
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a : &[Vec<f64>], b : &[Vec<f64>]) -> bool {
+        a.iter().flatten().zip(b.iter().flatten()).all(|(x,y)| (x-y).abs() < 1e-9)
+    }
+
+    #[test]
+    fn test_sherman_morrison() {
+        let mut a = vec![vec![2.0,0.5],vec![0.5,1.0]];
+        let mut inv = inverse(&a);
+        let x = [1.0,-2.0];
+        sherman_morrison(&mut inv,&x);
+        add_outer(&mut a,&x,&x,1.0);
+        assert!(close(&inv,&inverse(&a)));
+        assert!(close(&mat_mul(&a,&inv),&identity(2,1.0)));
+    }
+
+    #[test]
+    fn test_cholesky() {
+        let a = vec![vec![4.0,2.0],vec![2.0,3.0]];
+        let l = cholesky(&a);
+        assert!(close(&mat_mul(&l,&transpose(&l)),&a));
+    }
+}
@@ -0,0 +1,346 @@
+use crate::policies::{Policy,PolicyInit,PolicyEnum,NB_SAMPLES,create_policy};
+use crate::contextual::linalg::{self,Matrix};
+use crate::helper::{self,dot};
+
+use rand::Rng;
+use rand::prelude::IteratorRandom;
+use rand_distr::StandardNormal;
+use enum_dispatch::enum_dispatch;
+
+#[derive(Clone)]
+pub enum ContextualPolicyInit<'a> {
+  // Ignore the context, and use a context-free policy.
+  ContextFreeInit {policy : &'a PolicyInit<'a>},
+  // LinUCB with a separate ridge regression for each lever.
+  LinUCBInit {nb_levers : usize,
+              dim : usize,
+              alpha : f64,
+              regularization : f64},
+  // LinUCB with parameters shared by all levers on top of the
+  // parameters of each lever. The shared features are the context.
+  HybridLinUCBInit {nb_levers : usize,
+                    dim : usize,
+                    alpha : f64,
+                    regularization : f64},
+  // Thompson sampling from the gaussian posterior of the ridge
+  // regression of each lever, with variance scaled by scale^2.
+  LinTSInit {nb_levers : usize,
+             dim : usize,
+             scale : f64,
+             regularization : f64},
+}
+
+// The horizon is given to the context free policies.
+pub(crate) fn create_contextual_policy(init_data : &ContextualPolicyInit,
+                                       horizon : Option<usize>) -> ContextualPolicyEnum {
+  match *init_data {
+    ContextualPolicyInit::ContextFreeInit {policy} =>
+      ContextFree::new(policy,horizon).into(),
+    ContextualPolicyInit::LinUCBInit {nb_levers,dim,alpha,regularization} =>
+      LinUCB::new(nb_levers,dim,alpha,regularization).into(),
+    ContextualPolicyInit::HybridLinUCBInit {nb_levers,dim,alpha,regularization} =>
+      HybridLinUCB::new(nb_levers,dim,alpha,regularization).into(),
+    ContextualPolicyInit::LinTSInit {nb_levers,dim,scale,regularization} =>
+      LinTS::new(nb_levers,dim,scale,regularization).into(),
+  }
+}
+
+#[enum_dispatch]
+#[derive(Clone)]
+pub(crate) enum ContextualPolicyEnum {
+  ContextFree,
+  LinUCB,
+  HybridLinUCB,
+  LinTS,
+}
+
+#[enum_dispatch(ContextualPolicyEnum)]
+pub(crate) trait ContextualPolicy : Clone + Send {
+  // Choose a lever, knowing the context of the round.
+  fn decide<V: Rng>(&self, context : &[f64], rng: &mut V) -> usize;
+
+  // Update its values based on the result of the
+  // step.
+  fn update(&mut self, context : &[f64], lever : usize, reward : f64);
+
+  // Give the probability of choosing each lever in this context.
+  fn probabilities<V: Rng>(&self, context : &[f64], rng: &mut V) -> Vec<f64>;
+
+  // Check if probabilities are computed exactly, rather than
+  // estimated from sampled decisions.
+  fn exact_probabilities(&self) -> bool {
+    true
+  }
+}
+
+// Baseline which does not use the context at all.
+#[derive(Clone)]
+pub(crate) struct ContextFree {
+  policy : PolicyEnum,
+}
+
+impl ContextFree {
+
+  pub(crate) fn new(policy : &PolicyInit, horizon : Option<usize>) -> Self {
+    ContextFree {
+      policy : create_policy(policy,horizon),
+    }
+  }
+}
+
+impl ContextualPolicy for ContextFree {
+
+  fn decide<V: Rng>(&self, _context : &[f64], rng: &mut V) -> usize {
+    self.policy.decide(rng)
+  }
+
+  fn update(&mut self, _context : &[f64], lever : usize, reward : f64) {
+    self.policy.update(lever,reward);
+  }
+
+  fn probabilities<V: Rng>(&self, _context : &[f64], rng: &mut V) -> Vec<f64> {
+    self.policy.probabilities(rng)
+  }
+
+  fn exact_probabilities(&self) -> bool {
+    self.policy.exact_probabilities()
+  }
+}
+
+fn choose_max<V: Rng>(scores : &[f64], rng : &mut V) -> usize {
+  *helper::indices_max(scores)
+          .iter()
+          .choose(rng)
+          .unwrap()
+}
+
+// Ridge regression of the reward on the context, which keeps the
+// inverse of its design matrix up to date.
+#[derive(Clone)]
+struct Ridge {
+  inv : Matrix,
+  b : Vec<f64>,
+}
+
+impl Ridge {
+
+  fn new(dim : usize, regularization : f64) -> Self {
+    Ridge {
+      inv : linalg::identity(dim,1.0/regularization),
+      b : vec![0.0;dim],
+    }
+  }
+
+  fn theta(&self) -> Vec<f64> {
+    linalg::mat_vec(&self.inv,&self.b)
+  }
+
+  // Width of the confidence ellipsoid in the direction of x.
+  fn width(&self, x : &[f64]) -> f64 {
+    dot(x,&linalg::mat_vec(&self.inv,x)).sqrt()
+  }
+
+  fn update(&mut self, x : &[f64], reward : f64) {
+    linalg::sherman_morrison(&mut self.inv,x);
+    for (bi,xi) in self.b.iter_mut().zip(x.iter()) {
+      *bi += reward*xi;
+    }
+  }
+}
+
+#[derive(Clone)]
+pub(crate) struct LinUCB {
+  alpha : f64,
+  levers : Vec<Ridge>,
+}
+
+impl LinUCB {
+
+  pub(crate) fn new(nb_levers : usize, dim : usize, alpha : f64, regularization : f64) -> Self {
+    LinUCB {
+      alpha,
+      levers : vec![Ridge::new(dim,regularization);nb_levers],
+    }
+  }
+
+  fn indices(&self, context : &[f64]) -> Vec<f64> {
+    self.levers.iter()
+               .map(|ridge| dot(&ridge.theta(),context) + self.alpha*ridge.width(context))
+               .collect()
+  }
+}
+
+impl ContextualPolicy for LinUCB {
+
+  fn decide<V: Rng>(&self, context : &[f64], rng: &mut V) -> usize {
+    choose_max(&self.indices(context),rng)
+  }
+
+  fn update(&mut self, context : &[f64], lever : usize, reward : f64) {
+    self.levers[lever].update(context,reward);
+  }
+
+  fn probabilities<V: Rng>(&self, context : &[f64], _rng: &mut V) -> Vec<f64> {
+    helper::uniform_over(&helper::indices_max(&self.indices(context)),self.levers.len())
+  }
+}
+
+#[derive(Clone)]
+struct HybridLever {
+  inv : Matrix,
+  // Correlations between the context and the shared features.
+  shared : Matrix,
+  b : Vec<f64>,
+}
+
+// Algorithm 2 of Li et al., "A Contextual-Bandit Approach to
+// Personalized News Article Recommendation" (2010).
+#[derive(Clone)]
+pub(crate) struct HybridLinUCB {
+  alpha : f64,
+  a0 : Matrix,
+  a0_inv : Matrix,
+  b0 : Vec<f64>,
+  levers : Vec<HybridLever>,
+}
+
+impl HybridLinUCB {
+
+  pub(crate) fn new(nb_levers : usize, dim : usize, alpha : f64, regularization : f64) -> Self {
+    HybridLinUCB {
+      alpha,
+      a0 : linalg::identity(dim,regularization),
+      a0_inv : linalg::identity(dim,1.0/regularization),
+      b0 : vec![0.0;dim],
+      levers : vec![HybridLever { inv : linalg::identity(dim,1.0/regularization),
+                                  shared : linalg::zeros(dim,dim),
+                                  b : vec![0.0;dim] };
+                    nb_levers],
+    }
+  }
+
+  fn indices(&self, context : &[f64]) -> Vec<f64> {
+    let beta = linalg::mat_vec(&self.a0_inv,&self.b0);
+    let a0_inv_z = linalg::mat_vec(&self.a0_inv,context);
+    self.levers.iter()
+               .map(|lever| {
+                 let shared_beta = linalg::mat_vec(&lever.shared,&beta);
+                 let residual : Vec<f64> = lever.b.iter()
+                                                  .zip(shared_beta.iter())
+                                                  .map(|(b,s)| b-s)
+                                                  .collect();
+                 let theta = linalg::mat_vec(&lever.inv,&residual);
+                 let inv_x = linalg::mat_vec(&lever.inv,context);
+                 let u = linalg::mat_t_vec(&lever.shared,&inv_x);
+                 let var = dot(context,&a0_inv_z)
+                           - 2.0*dot(&a0_inv_z,&u)
+                           + dot(context,&inv_x)
+                           + dot(&u,&linalg::mat_vec(&self.a0_inv,&u));
+                 dot(context,&beta) + dot(context,&theta) + self.alpha*var.max(0.0).sqrt()
+               })
+               .collect()
+  }
+
+  // Add (or remove, with sign -1) the contribution of a lever
+  // to the shared regression.
+  fn share(&mut self, lever : usize, sign : f64) {
+    let lever = &self.levers[lever];
+    let shared_t = linalg::transpose(&lever.shared);
+    let shared_t_inv = linalg::mat_mul(&shared_t,&lever.inv);
+    linalg::add(&mut self.a0,&linalg::mat_mul(&shared_t_inv,&lever.shared),sign);
+    for (b0,x) in self.b0.iter_mut().zip(linalg::mat_vec(&shared_t_inv,&lever.b)) {
+      *b0 += sign*x;
+    }
+  }
+}
+
+impl ContextualPolicy for HybridLinUCB {
+
+  fn decide<V: Rng>(&self, context : &[f64], rng: &mut V) -> usize {
+    choose_max(&self.indices(context),rng)
+  }
+
+  fn update(&mut self, context : &[f64], lever : usize, reward : f64) {
+    self.share(lever,1.0);
+    {
+      let lever = &mut self.levers[lever];
+      linalg::sherman_morrison(&mut lever.inv,context);
+      linalg::add_outer(&mut lever.shared,context,context,1.0);
+      for (bi,xi) in lever.b.iter_mut().zip(context.iter()) {
+        *bi += reward*xi;
+      }
+    }
+    self.share(lever,-1.0);
+    linalg::add_outer(&mut self.a0,context,context,1.0);
+    for (b0,zi) in self.b0.iter_mut().zip(context.iter()) {
+      *b0 += reward*zi;
+    }
+    // The shared matrix changes by more than a rank one update.
+    self.a0_inv = linalg::inverse(&self.a0);
+  }
+
+  fn probabilities<V: Rng>(&self, context : &[f64], _rng: &mut V) -> Vec<f64> {
+    helper::uniform_over(&helper::indices_max(&self.indices(context)),self.levers.len())
+  }
+}
+
+#[derive(Clone)]
+pub(crate) struct LinTS {
+  scale : f64,
+  levers : Vec<Ridge>,
+}
+
+impl LinTS {
+
+  pub(crate) fn new(nb_levers : usize, dim : usize, scale : f64, regularization : f64) -> Self {
+    LinTS {
+      scale,
+      levers : vec![Ridge::new(dim,regularization);nb_levers],
+    }
+  }
+
+  // Posterior mean and cholesky factor of the posterior
+  // covariance of each lever.
+  fn posteriors(&self) -> Vec<(Vec<f64>,Matrix)> {
+    self.levers.iter()
+               .map(|ridge| (ridge.theta(),linalg::cholesky(&ridge.inv)))
+               .collect()
+  }
+
+  fn sample<V: Rng>(&self, posteriors : &[(Vec<f64>,Matrix)], context : &[f64], rng : &mut V) -> usize {
+    let scores : Vec<f64> =
+      posteriors.iter()
+                .map(|(theta,chol)| {
+                  let noise : Vec<f64> = (0..theta.len()).map(|_| rng.sample(StandardNormal))
+                                                         .collect();
+                  dot(theta,context) + self.scale*dot(&linalg::mat_t_vec(chol,context),&noise)
+                })
+                .collect();
+    choose_max(&scores,rng)
+  }
+}
+
+impl ContextualPolicy for LinTS {
+
+  fn decide<V: Rng>(&self, context : &[f64], rng: &mut V) -> usize {
+    self.sample(&self.posteriors(),context,rng)
+  }
+
+  fn update(&mut self, context : &[f64], lever : usize, reward : f64) {
+    self.levers[lever].update(context,reward);
+  }
+
+  // Estimated from NB_SAMPLES sampled decisions.
+  fn probabilities<V: Rng>(&self, context : &[f64], rng: &mut V) -> Vec<f64> {
+    let posteriors = self.posteriors();
+    let mut probas = vec![0.0;self.levers.len()];
+    for _ in 0..NB_SAMPLES {
+      probas[self.sample(&posteriors,context,rng)] += 1.0/(NB_SAMPLES as f64);
+    }
+    probas
+  }
+
+  fn exact_probabilities(&self) -> bool {
+    false
+  }
+}
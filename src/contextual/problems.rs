@@ -0,0 +1,158 @@
+use crate::helper;
+
+use std::collections::HashSet;
+use std::iter::FromIterator;
+
+use rand::Rng;
+use rand_distr::{Normal, Distribution};
+use enum_dispatch::enum_dispatch;
+
+#[derive(Clone)]
+pub enum ContextualBanditInit {
+  // Rewards are the dot product of the context with the parameters
+  // of the lever, plus a gaussian noise of std noise.
+  LinearInit { nb_levers : usize,
+               dim : usize,
+               params : (f64,f64),
+               noise : f64
+             },
+  // Rewards are 0 or 1, with the logistic function of the dot
+  // product of the context with the parameters as probability of 1.
+  LogisticInit { nb_levers : usize,
+                 dim : usize,
+                 params : (f64,f64)
+               },
+}
+
+pub(crate) fn create_contextual_bandit<T : Rng>(init_data : &ContextualBanditInit,
+                                                rng : &mut T) -> ContextualBanditEnum {
+  match *init_data {
+    ContextualBanditInit::LinearInit {nb_levers,dim,params,noise} =>
+      ContextualLinear::new(nb_levers,dim,params,noise,rng).into(),
+    ContextualBanditInit::LogisticInit {nb_levers,dim,params} =>
+      ContextualLogistic::new(nb_levers,dim,params,rng).into(),
+  }
+}
+
+#[enum_dispatch]
+#[derive(Clone)]
+pub(crate) enum ContextualBanditEnum {
+  ContextualLinear,
+  ContextualLogistic,
+}
+
+#[enum_dispatch(ContextualBanditEnum)]
+pub(crate) trait ContextualBandit : Clone + Send {
+  // Draw the context of the next round.
+  fn context<T: Rng>(&mut self, rng: &mut T) -> Vec<f64>;
+
+  // Get reward from a lever, in the context of the current round.
+  fn use_lever<T: Rng>(&mut self, lever : usize, rng: &mut T) -> f64;
+
+  // Check if the lever is optimal in the context of the current round.
+  fn is_optimal(&self, lever : usize) -> bool;
+}
+
+// Parameters of each lever, drawn according to a normal distribution.
+fn sample_params<T: Rng>(nb_levers : usize, dim : usize, params : (f64,f64), rng : &mut T) -> Vec<Vec<f64>> {
+  let distrib = Normal::new(params.0,params.1).unwrap();
+  (0..nb_levers).map(|_| distrib.sample_iter(&mut *rng).take(dim).collect())
+                .collect()
+}
+
+// Contexts have independent gaussian coordinates, scaled so that
+// their expected squared norm is 1.
+fn sample_context<T: Rng>(dim : usize, rng : &mut T) -> Vec<f64> {
+  let distrib = Normal::new(0.0,1.0/(dim as f64).sqrt()).unwrap();
+  distrib.sample_iter(rng).take(dim).collect()
+}
+
+fn optimals(params : &[Vec<f64>], context : &[f64]) -> HashSet<usize> {
+  let scores : Vec<f64> = params.iter().map(|x| helper::dot(x,context)).collect();
+  HashSet::from_iter(helper::indices_max(&scores))
+}
+
+// Implementation of a contextual bandit with linear rewards.
+#[derive(Clone)]
+pub(crate) struct ContextualLinear {
+  params : Vec<Vec<f64>>,
+  noise : Normal<f64>,
+  context : Vec<f64>,
+  optimals : HashSet<usize>,
+}
+
+impl ContextualLinear {
+
+  pub(crate) fn new<T: Rng>(nb_levers : usize,
+                            dim : usize,
+                            params : (f64,f64),
+                            noise : f64,
+                            rng : &mut T) -> Self {
+    ContextualLinear {
+      params : sample_params(nb_levers,dim,params,rng),
+      noise : Normal::new(0.0,noise).unwrap(),
+      context : vec![0.0;dim],
+      optimals : HashSet::new(),
+    }
+  }
+}
+
+impl ContextualBandit for ContextualLinear {
+
+  fn context<T: Rng>(&mut self, rng: &mut T) -> Vec<f64> {
+    self.context = sample_context(self.context.len(),rng);
+    self.optimals = optimals(&self.params,&self.context);
+    self.context.clone()
+  }
+
+  fn use_lever<T: Rng>(&mut self, lever : usize, rng: &mut T) -> f64 {
+    helper::dot(&self.params[lever],&self.context) + self.noise.sample(rng)
+  }
+
+  fn is_optimal(&self, lever : usize) -> bool {
+    self.optimals.contains(&lever)
+  }
+}
+
+// Implementation of a contextual bandit with binary rewards,
+// following a logistic model.
+#[derive(Clone)]
+pub(crate) struct ContextualLogistic {
+  params : Vec<Vec<f64>>,
+  context : Vec<f64>,
+  optimals : HashSet<usize>,
+}
+
+impl ContextualLogistic {
+
+  pub(crate) fn new<T: Rng>(nb_levers : usize,
+                            dim : usize,
+                            params : (f64,f64),
+                            rng : &mut T) -> Self {
+    ContextualLogistic {
+      params : sample_params(nb_levers,dim,params,rng),
+      context : vec![0.0;dim],
+      optimals : HashSet::new(),
+    }
+  }
+}
+
+impl ContextualBandit for ContextualLogistic {
+
+  fn context<T: Rng>(&mut self, rng: &mut T) -> Vec<f64> {
+    self.context = sample_context(self.context.len(),rng);
+    // The logistic function is increasing, so the best lever
+    // is the one with the best dot product.
+    self.optimals = optimals(&self.params,&self.context);
+    self.context.clone()
+  }
+
+  fn use_lever<T: Rng>(&mut self, lever : usize, rng: &mut T) -> f64 {
+    let proba = 1.0 / (1.0 + (-helper::dot(&self.params[lever],&self.context)).exp());
+    (rng.gen_bool(proba) as usize) as f64
+  }
+
+  fn is_optimal(&self, lever : usize) -> bool {
+    self.optimals.contains(&lever)
+  }
+}
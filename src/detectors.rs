@@ -0,0 +1,189 @@
+use crate::helper;
+
+use std::collections::VecDeque;
+
+use serde::{Serialize,Deserialize};
+
+// Change detectors watch the rewards of one lever, and raise an
+// alarm when their distribution seems to have changed. The
+// policies restart their statistics for the lever at an alarm.
+
+// Only splits of the history at multiples of this stride are
+// tested by the GLR detector, to keep it tractable on long runs.
+const GLR_STRIDE : usize = 5;
+
+// The GLR detector only keeps this many rewards, so that its memory
+// and the cost of an update stay bounded.
+const GLR_HISTORY : usize = 1000;
+
+// Two-sided CUSUM test, from "A Change-Detection based Framework for
+// Piecewise-stationary Multi-Armed Bandit Problem" (Liu et al., 2018).
+// The mean is estimated on the first samples, and then deviations
+// from it larger than the drift are accumulated.
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct Cusum {
+  warmup : usize,
+  drift : f64,
+  threshold : f64,
+  nb : usize,
+  mean : f64,
+  pos : f64,
+  neg : f64,
+}
+
+impl Cusum {
+
+  pub(crate) fn new(warmup : usize, drift : f64, threshold : f64) -> Self {
+    Cusum {
+      warmup,
+      drift,
+      threshold,
+      nb : 0,
+      mean : 0.0,
+      pos : 0.0,
+      neg : 0.0,
+    }
+  }
+
+  // Add a reward, and check for a change.
+  pub(crate) fn update(&mut self, reward : f64) -> bool {
+    self.nb += 1;
+    if self.nb <= self.warmup {
+      self.mean += (reward - self.mean) / (self.nb as f64);
+      false
+    } else {
+      self.pos = (self.pos + reward - self.mean - self.drift).max(0.0);
+      self.neg = (self.neg + self.mean - reward - self.drift).max(0.0);
+      self.pos > self.threshold || self.neg > self.threshold
+    }
+  }
+
+  pub(crate) fn reset(&mut self) {
+    *self = Cusum::new(self.warmup,self.drift,self.threshold);
+  }
+}
+
+// Bernoulli generalized likelihood ratio test, from "Efficient
+// Change-Point Detection for Tackling Piecewise-Stationary Bandits"
+// (Besson et al., 2019). Rewards are clamped to [0,1]. Changes are
+// looked for among the last GLR_HISTORY rewards.
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct GLR {
+  confidence : f64,
+  // Prefix sums of the rewards since the last reset. The first one
+  // is the sum of the rewards dropped from the history.
+  sums : VecDeque<f64>,
+}
+
+impl GLR {
+
+  pub(crate) fn new(confidence : f64) -> Self {
+    GLR {
+      confidence,
+      sums : VecDeque::from(vec![0.0]),
+    }
+  }
+
+  // Add a reward, and check for a change.
+  pub(crate) fn update(&mut self, reward : f64) -> bool {
+    let last = self.sums[self.sums.len()-1] + reward.clamp(0.0,1.0);
+    self.sums.push_back(last);
+    while self.sums.len() > GLR_HISTORY + 1 {
+      self.sums.pop_front();
+    }
+    let nb = self.sums.len() - 1;
+    if nb < 2 {
+      return false;
+    }
+    let n = nb as f64;
+    let threshold = (3.0*n.powf(1.5)/self.confidence).ln();
+    let total = last - self.sums[0];
+    let mean = total / n;
+    (1..nb).filter(|split| split % GLR_STRIDE == 0 || *split == nb-1)
+           .any(|split| {
+             let s = split as f64;
+             let before = (self.sums[split] - self.sums[0]) / s;
+             let after = (last - self.sums[split]) / (n - s);
+             s*helper::kl_bernoulli(before,mean)
+               + (n-s)*helper::kl_bernoulli(after,mean) > threshold
+           })
+  }
+
+  pub(crate) fn reset(&mut self) {
+    self.sums = VecDeque::from(vec![0.0]);
+  }
+}
+
+// Test of M-UCB, from "Nearly Optimal Adaptive Procedure with Change
+// Detection for Piecewise-Stationary Bandit" (Cao et al., 2019): the
+// sums of the two halves of the last window rewards are compared.
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct WindowTest {
+  window : usize,
+  threshold : f64,
+  rewards : VecDeque<f64>,
+}
+
+impl WindowTest {
+
+  pub(crate) fn new(window : usize, threshold : f64) -> Self {
+    WindowTest {
+      window,
+      threshold,
+      rewards : VecDeque::with_capacity(window+1),
+    }
+  }
+
+  // Add a reward, and check for a change.
+  pub(crate) fn update(&mut self, reward : f64) -> bool {
+    self.rewards.push_back(reward);
+    if self.rewards.len() > self.window {
+      self.rewards.pop_front();
+    }
+    if self.rewards.len() < self.window {
+      return false;
+    }
+    let half = self.window / 2;
+    let first : f64 = self.rewards.iter().take(half).sum();
+    let second : f64 = self.rewards.iter().skip(half).take(half).sum();
+    (second - first).abs() > self.threshold
+  }
+
+  pub(crate) fn reset(&mut self) {
+    self.rewards.clear();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_shift() {
+        let mut cusum = Cusum::new(10,0.05,2.0);
+        let mut glr = GLR::new(0.01);
+        let mut window = WindowTest::new(20,4.0);
+        let rewards = (0..200).map(|t| if t < 100 { 0.2 } else { 0.9 });
+        let alarms : Vec<(usize,bool,bool,bool)> =
+          rewards.enumerate()
+                 .map(|(t,r)| (t,cusum.update(r),glr.update(r),window.update(r)))
+                 .collect();
+        for detector in 0..3 {
+            let first = alarms.iter()
+                              .find(|x| [x.1,x.2,x.3][detector])
+                              .map(|x| x.0)
+                              .unwrap();
+            assert!((100..120).contains(&first), "{} {}", detector, first);
+        }
+    }
+
+    #[test]
+    fn test_glr_history() {
+        let mut glr = GLR::new(0.01);
+        let alarms = (0..3*GLR_HISTORY).filter(|t| glr.update((t % 2) as f64)).count();
+        assert_eq!(alarms,0);
+        assert_eq!(glr.sums.len(),GLR_HISTORY + 1);
+        // A change long after the start is still detected.
+        assert!((0..100).any(|_| glr.update(1.0)));
+    }
+}
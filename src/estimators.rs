@@ -1,27 +1,200 @@
 use crate::helper;
+use crate::snapshot::floats;
+
+use std::collections::VecDeque;
+
+use rand::Rng;
+use rand_distr::{Beta, Distribution, Gamma, Normal};
 use enum_dispatch::enum_dispatch;
+use serde::{Serialize,Deserialize};
+
+// Estimates of the levers before any pull. They can be infinite, for
+// an optimistic start, and are then written as strings in snapshots.
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+pub enum InitialValues {
+  Uniform(#[serde(with = "floats")] f64),
+  PerLever(#[serde(with = "floats::vec")] Vec<f64>),
+}
+
+impl InitialValues {
+
+  pub(crate) fn values(&self, nb_levers : usize) -> Vec<f64> {
+    match self {
+      InitialValues::Uniform(value) => vec![*value;nb_levers],
+      InitialValues::PerLever(values) => {
+        assert_eq!(values.len(), nb_levers, "One initial value is needed for each lever.");
+        values.clone()
+      },
+    }
+  }
+}
+
+impl Default for InitialValues {
+
+  fn default() -> Self {
+    InitialValues::Uniform(0.0)
+  }
+}
+
+// Step size of the n-th update of a lever, starting at n = 1.
+#[derive(Clone,Copy,Debug,PartialEq,Serialize,Deserialize)]
+pub enum StepSize {
+  // 1/n^exponent: the sample average for exponent 1. Robbins-Monro
+  // conditions hold for an exponent in (0.5,1], so 1 is the largest
+  // exponent converging in the stationary case.
+  Power {exponent : f64},
+  // alpha/(1 + beta*n).
+  Rational {alpha : f64,
+            beta : f64},
+  // scale/(scale + n - 1), which starts at 1 and then decreases
+  // more slowly than 1/n for a large scale.
+  Harmonic {scale : f64},
+  // Kesten's rule: alpha/(beta + k), where k is the number of sign
+  // changes of the error, so the step only decreases once the
+  // estimate oscillates around the mean.
+  Kesten {alpha : f64,
+          beta : f64},
+}
 
-#[derive(Clone)]
+#[derive(Clone,Serialize,Deserialize)]
 pub enum EstimatorInit {
   SampleAverageInit {nb_levers : usize},
   ConstantStepInit {nb_levers : usize,
                     step : f64},
+  // Sample average starting from the initial values, which count
+  // as pseudo_counts rewards. Without pseudo counts, the initial
+  // values are forgotten at the first pull.
+  PriorSampleAverageInit {nb_levers : usize,
+                          init : InitialValues,
+                          pseudo_counts : f64},
+  // Constant step starting from the initial values, which are
+  // forgotten at an exponential rate.
+  PriorConstantStepInit {nb_levers : usize,
+                         step : f64,
+                         init : InitialValues},
+  // Constant step without the bias of the initial values, from
+  // exercise 2.7 of Sutton & Barto: the step starts at 1 and
+  // decreases to step.
+  UnbiasedConstantStepInit {nb_levers : usize,
+                            step : f64,
+                            init : InitialValues},
+  // Normal rewards of known variance noise_var, with a normal
+  // prior on their mean.
+  NormalInit {nb_levers : usize,
+              prior_mean : f64,
+              prior_var : f64,
+              noise_var : f64},
+  // Normal rewards of unknown mean and precision, with a
+  // Normal-Gamma prior: the precision follows Gamma(shape,rate),
+  // and the mean a normal of precision strength times it.
+  NormalGammaInit {nb_levers : usize,
+                   prior_mean : f64,
+                   strength : f64,
+                   shape : f64,
+                   rate : f64},
+  // Rewards in [0,1] (usually 0 or 1), with a Beta(alpha,beta)
+  // prior on their mean.
+  BetaBernoulliInit {nb_levers : usize,
+                     alpha : f64,
+                     beta : f64},
+  // Poisson rewards, with a Gamma(shape,rate) prior on their mean.
+  GammaPoissonInit {nb_levers : usize,
+                    shape : f64,
+                    rate : f64},
+  // Incremental estimate starting from the initial values, with
+  // steps following the schedule.
+  ScheduledStepInit {nb_levers : usize,
+                     steps : StepSize,
+                     init : InitialValues},
+  // Average of the last window rewards of each lever.
+  SlidingWindowInit {nb_levers : usize,
+                     window : usize},
+  // Discounted average: at each pull, the past rewards and counts
+  // of every lever are multiplied by discount.
+  DiscountedInit {nb_levers : usize,
+                  discount : f64},
+  // Robust estimators for heavy-tailed rewards.
+  // Median of the means of nb_blocks blocks of rewards, the n-th
+  // reward going to the block n modulo nb_blocks.
+  MedianOfMeansInit {nb_levers : usize,
+                     nb_blocks : usize},
+  // Average where the n-th reward is dropped when larger than
+  // (moment*n/ln(1/confidence))^(1/(1+epsilon)), for rewards with
+  // a moment of order 1+epsilon at most moment.
+  TruncatedMeanInit {nb_levers : usize,
+                     epsilon : f64,
+                     moment : f64,
+                     confidence : f64},
+  // Catoni M-estimator, for rewards of variance at most variance,
+  // computed on the last window rewards of each lever.
+  CatoniInit {nb_levers : usize,
+              variance : f64,
+              confidence : f64,
+              window : usize},
+  // Kalman filter for means following independent gaussian random
+  // walks of variance walk_var at each tick, observed with a noise
+  // of variance obs_var, as in the non stationary bandit.
+  KalmanInit {nb_levers : usize,
+              init_mean : f64,
+              init_var : f64,
+              walk_var : f64,
+              obs_var : f64},
 }
 
 pub(crate) fn create_estimator(init_data : &EstimatorInit) -> EstimatorEnum {
   match *init_data {
     EstimatorInit::SampleAverageInit {nb_levers} =>
-      SampleAverage::new(nb_levers).into(),
+      SampleAverage::new(nb_levers,&InitialValues::default(),0.0).into(),
     EstimatorInit::ConstantStepInit {nb_levers, step} =>
-      ConstantStep::new(nb_levers,step).into(),
+      ConstantStep::new(nb_levers,step,&InitialValues::default()).into(),
+    EstimatorInit::PriorSampleAverageInit {nb_levers, ref init, pseudo_counts} =>
+      SampleAverage::new(nb_levers,init,pseudo_counts).into(),
+    EstimatorInit::PriorConstantStepInit {nb_levers, step, ref init} =>
+      ConstantStep::new(nb_levers,step,init).into(),
+    EstimatorInit::UnbiasedConstantStepInit {nb_levers, step, ref init} =>
+      UnbiasedConstantStep::new(nb_levers,step,init).into(),
+    EstimatorInit::NormalInit {nb_levers, prior_mean, prior_var, noise_var} =>
+      NormalPosterior::new(nb_levers,prior_mean,prior_var,noise_var).into(),
+    EstimatorInit::NormalGammaInit {nb_levers, prior_mean, strength, shape, rate} =>
+      NormalGammaPosterior::new(nb_levers,prior_mean,strength,shape,rate).into(),
+    EstimatorInit::BetaBernoulliInit {nb_levers, alpha, beta} =>
+      BetaPosterior::new(nb_levers,alpha,beta).into(),
+    EstimatorInit::GammaPoissonInit {nb_levers, shape, rate} =>
+      GammaPosterior::new(nb_levers,shape,rate).into(),
+    EstimatorInit::ScheduledStepInit {nb_levers, steps, ref init} =>
+      ScheduledStep::new(nb_levers,steps,init).into(),
+    EstimatorInit::SlidingWindowInit {nb_levers, window} =>
+      SlidingWindow::new(nb_levers,window).into(),
+    EstimatorInit::DiscountedInit {nb_levers, discount} =>
+      Discounted::new(nb_levers,discount).into(),
+    EstimatorInit::MedianOfMeansInit {nb_levers, nb_blocks} =>
+      MedianOfMeans::new(nb_levers,nb_blocks).into(),
+    EstimatorInit::TruncatedMeanInit {nb_levers, epsilon, moment, confidence} =>
+      TruncatedMean::new(nb_levers,epsilon,moment,confidence).into(),
+    EstimatorInit::CatoniInit {nb_levers, variance, confidence, window} =>
+      Catoni::new(nb_levers,variance,confidence,window).into(),
+    EstimatorInit::KalmanInit {nb_levers, init_mean, init_var, walk_var, obs_var} =>
+      Kalman::new(nb_levers,init_mean,init_var,walk_var,obs_var).into(),
   }
 }
 
 #[enum_dispatch]
-#[derive(Clone)]
+#[derive(Clone,Serialize,Deserialize)]
 pub(crate) enum EstimatorEnum {
   SampleAverage,
   ConstantStep,
+  UnbiasedConstantStep,
+  ScheduledStep,
+  SlidingWindow,
+  Discounted,
+  MedianOfMeans,
+  TruncatedMean,
+  Catoni,
+  NormalPosterior,
+  NormalGammaPosterior,
+  BetaPosterior,
+  GammaPosterior,
+  Kalman,
 }
 
 #[enum_dispatch(EstimatorEnum)]
@@ -33,6 +206,91 @@ pub(crate) trait Estimator : Send {
   // the reward.
   fn update(&mut self, lever : usize, reward : f64);
 
+  // Forget everything learned about the lever.
+  fn reset(&mut self, lever : usize);
+
+  // The environment moved by one tick. Only estimators of drifting
+  // rewards need to know it.
+  fn tick(&mut self) {}
+
+  // Number of levers estimated.
+  fn nb_levers(&self) -> usize;
+
+  // Statistics of the rewards received by the lever since its
+  // last reset.
+  fn stats(&self, lever : usize) -> &LeverStats;
+
+  // Number of pulls of the lever since its last reset.
+  fn count(&self, lever : usize) -> f64 {
+    self.stats(lever).count
+  }
+
+  // Empirical variance of the rewards of the lever.
+  fn reward_variance(&self, lever : usize) -> f64 {
+    self.stats(lever).variance()
+  }
+
+  // Number of rewards the estimate of the lever is worth: lower
+  // than the count for estimators forgetting old rewards.
+  fn effective_count(&self, lever : usize) -> f64 {
+    self.count(lever)
+  }
+
+  // Bounds on the mean of the lever, each holding with probability
+  // confidence, like 0.95. By default, they come from the normal
+  // approximation of the empirical mean, and are infinite for
+  // levers never pulled.
+  fn upper_bound(&self, lever : usize, confidence : f64) -> f64 {
+    let count = self.effective_count(lever);
+    if count > 0.0 {
+      self.estimate(lever)
+        + helper::normal_quantile(confidence)*(self.reward_variance(lever)/count).sqrt()
+    } else {
+      f64::INFINITY
+    }
+  }
+
+  fn lower_bound(&self, lever : usize, confidence : f64) -> f64 {
+    let count = self.effective_count(lever);
+    if count > 0.0 {
+      self.estimate(lever)
+        - helper::normal_quantile(confidence)*(self.reward_variance(lever)/count).sqrt()
+    } else {
+      f64::NEG_INFINITY
+    }
+  }
+
+  // Interval containing the mean of the lever with probability level.
+  fn credible_interval(&self, lever : usize, level : f64) -> (f64,f64) {
+    (self.lower_bound(lever,(1.0+level)/2.0), self.upper_bound(lever,(1.0+level)/2.0))
+  }
+
+  // Give the effective counts of all levers.
+  fn effective_counts(&self, nb_levers : usize) -> Vec<f64> {
+    (0..nb_levers).map(|x| self.effective_count(x))
+                  .collect()
+  }
+
+  // The next methods describe the distribution of the mean of
+  // the lever. Point estimates are a point mass on the estimate,
+  // bayesian estimators give their posterior.
+
+  // Variance of the distribution of the mean of the lever.
+  fn variance(&self, _lever : usize) -> f64 {
+    0.0
+  }
+
+  // Quantile of order p of the distribution of the mean of the lever.
+  fn quantile(&self, lever : usize, _p : f64) -> f64 {
+    self.estimate(lever)
+  }
+
+  // Sample the mean of the lever from its distribution.
+  fn sample<V: Rng>(&self, lever : usize, _rng : &mut V) -> f64 {
+    self.estimate(lever)
+  }
+
+
   // Give all estimates.
   fn all(&self, nb_levers : usize) -> Vec<f64> {
     (0..nb_levers).map(|x| self.estimate(x))
@@ -45,18 +303,53 @@ pub(crate) trait Estimator : Send {
   }
 }
 
-#[derive(Clone)]
+// Running statistics of the rewards of a lever: number, mean and
+// sum of squared deviations, updated with Welford's algorithm.
+#[derive(Clone,Copy,Default,Debug,PartialEq,Serialize,Deserialize)]
+pub(crate) struct LeverStats {
+  count : f64,
+  mean : f64,
+  m2 : f64,
+}
+
+impl LeverStats {
+
+  pub(crate) fn add(&mut self, reward : f64) {
+    self.count += 1.0;
+    let delta = reward - self.mean;
+    self.mean += delta / self.count;
+    self.m2 += delta*(reward - self.mean);
+  }
+
+  pub(crate) fn mean(&self) -> f64 {
+    self.mean
+  }
+
+  // Unbiased variance, zero with less than two rewards.
+  pub(crate) fn variance(&self) -> f64 {
+    if self.count > 1.0 {
+      self.m2 / (self.count - 1.0)
+    } else {
+      0.0
+    }
+  }
+}
+
+#[derive(Clone,Serialize,Deserialize)]
 pub(crate) struct SampleAverage {
-  counter : Vec<f64>,
-  estimates : Vec<f64>,
+  #[serde(with = "floats::vec")]
+  init : Vec<f64>,
+  pseudo_counts : f64,
+  stats : Vec<LeverStats>,
 }
 
 impl SampleAverage {
 
-  pub(crate) fn new(nb_levers : usize) -> Self {
+  pub(crate) fn new(nb_levers : usize, init : &InitialValues, pseudo_counts : f64) -> Self {
     SampleAverage {
-      counter : vec![1.0;nb_levers],
-      estimates : vec![0.0;nb_levers],
+      init : init.values(nb_levers),
+      pseudo_counts,
+      stats : vec![LeverStats::default();nb_levers],
     }
   }
 }
@@ -64,29 +357,52 @@ impl SampleAverage {
 impl Estimator for SampleAverage {
 
   fn estimate(&self, lever : usize) -> f64 {
-    self.estimates[lever]
+    let stats = &self.stats[lever];
+    if stats.count + self.pseudo_counts > 0.0 {
+      (self.pseudo_counts*self.init[lever] + stats.count*stats.mean())
+        / (self.pseudo_counts + stats.count)
+    } else {
+      self.init[lever]
+    }
   }
 
   fn update(&mut self, lever : usize, reward : f64) {
-    self.estimates[lever] =
-      self.estimates[lever] + (reward - self.estimates[lever])/self.counter[lever];
-    self.counter[lever] += 1.0;
+    self.stats[lever].add(reward);
+  }
+
+  fn reset(&mut self, lever : usize) {
+    self.stats[lever] = LeverStats::default();
+  }
+
+  fn nb_levers(&self) -> usize {
+    self.stats.len()
+  }
+
+  fn stats(&self, lever : usize) -> &LeverStats {
+    &self.stats[lever]
   }
 }
 
 
-#[derive(Clone)]
+#[derive(Clone,Serialize,Deserialize)]
 pub(crate) struct ConstantStep {
   step : f64,
+  #[serde(with = "floats::vec")]
+  init : Vec<f64>,
+  #[serde(with = "floats::vec")]
   estimates : Vec<f64>,
+  stats : Vec<LeverStats>,
 }
 
 impl ConstantStep {
 
-  pub(crate) fn new(nb_levers : usize, step : f64) -> Self {
+  pub(crate) fn new(nb_levers : usize, step : f64, init : &InitialValues) -> Self {
+    let init = init.values(nb_levers);
     ConstantStep {
       step,
-      estimates : vec![0.0;nb_levers],
+      estimates : init.clone(),
+      init,
+      stats : vec![LeverStats::default();nb_levers],
     }
   }
 }
@@ -98,7 +414,1025 @@ impl Estimator for ConstantStep {
   }
 
   fn update(&mut self, lever : usize, reward : f64) {
+    self.stats[lever].add(reward);
     self.estimates[lever] =
       self.estimates[lever] + self.step*(reward - self.estimates[lever]);
   }
+
+  fn reset(&mut self, lever : usize) {
+    self.stats[lever] = LeverStats::default();
+    self.estimates[lever] = self.init[lever];
+  }
+
+  fn nb_levers(&self) -> usize {
+    self.estimates.len()
+  }
+
+  fn stats(&self, lever : usize) -> &LeverStats {
+    &self.stats[lever]
+  }
+}
+
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct UnbiasedConstantStep {
+  step : f64,
+  #[serde(with = "floats::vec")]
+  init : Vec<f64>,
+  #[serde(with = "floats::vec")]
+  estimates : Vec<f64>,
+  // Trace of the step of each lever: the step used is step/trace,
+  // with a trace going from 0 to 1.
+  traces : Vec<f64>,
+  stats : Vec<LeverStats>,
+}
+
+impl UnbiasedConstantStep {
+
+  pub(crate) fn new(nb_levers : usize, step : f64, init : &InitialValues) -> Self {
+    let init = init.values(nb_levers);
+    UnbiasedConstantStep {
+      step,
+      estimates : init.clone(),
+      init,
+      traces : vec![0.0;nb_levers],
+      stats : vec![LeverStats::default();nb_levers],
+    }
+  }
+}
+
+impl Estimator for UnbiasedConstantStep {
+
+  fn estimate(&self, lever : usize) -> f64 {
+    self.estimates[lever]
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.stats[lever].add(reward);
+    self.traces[lever] += self.step*(1.0 - self.traces[lever]);
+    self.estimates[lever] +=
+      self.step / self.traces[lever] * (reward - self.estimates[lever]);
+  }
+
+  fn reset(&mut self, lever : usize) {
+    self.stats[lever] = LeverStats::default();
+    self.traces[lever] = 0.0;
+    self.estimates[lever] = self.init[lever];
+  }
+
+  fn nb_levers(&self) -> usize {
+    self.estimates.len()
+  }
+
+  fn stats(&self, lever : usize) -> &LeverStats {
+    &self.stats[lever]
+  }
+}
+
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct ScheduledStep {
+  steps : StepSize,
+  #[serde(with = "floats::vec")]
+  init : Vec<f64>,
+  #[serde(with = "floats::vec")]
+  estimates : Vec<f64>,
+  // Last error and number of sign changes of the error of each
+  // lever, for Kesten's rule.
+  errors : Vec<(f64,f64)>,
+  stats : Vec<LeverStats>,
+}
+
+impl ScheduledStep {
+
+  pub(crate) fn new(nb_levers : usize, steps : StepSize, init : &InitialValues) -> Self {
+    let init = init.values(nb_levers);
+    ScheduledStep {
+      steps,
+      estimates : init.clone(),
+      init,
+      errors : vec![(0.0,0.0);nb_levers],
+      stats : vec![LeverStats::default();nb_levers],
+    }
+  }
+
+  fn step(&self, lever : usize) -> f64 {
+    let n = self.stats[lever].count;
+    match self.steps {
+      StepSize::Power {exponent} => n.powf(-exponent),
+      StepSize::Rational {alpha,beta} => alpha / (1.0 + beta*n),
+      StepSize::Harmonic {scale} => scale / (scale + n - 1.0),
+      StepSize::Kesten {alpha,beta} => alpha / (beta + self.errors[lever].1),
+    }
+  }
+}
+
+impl Estimator for ScheduledStep {
+
+  fn estimate(&self, lever : usize) -> f64 {
+    self.estimates[lever]
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.stats[lever].add(reward);
+    let error = reward - self.estimates[lever];
+    if error*self.errors[lever].0 < 0.0 {
+      self.errors[lever].1 += 1.0;
+    }
+    self.errors[lever].0 = error;
+    self.estimates[lever] += self.step(lever)*error;
+  }
+
+  fn reset(&mut self, lever : usize) {
+    self.stats[lever] = LeverStats::default();
+    self.estimates[lever] = self.init[lever];
+    self.errors[lever] = (0.0,0.0);
+  }
+
+  fn nb_levers(&self) -> usize {
+    self.estimates.len()
+  }
+
+  fn stats(&self, lever : usize) -> &LeverStats {
+    &self.stats[lever]
+  }
+}
+
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct SlidingWindow {
+  window : usize,
+  rewards : Vec<VecDeque<f64>>,
+  stats : Vec<LeverStats>,
+}
+
+impl SlidingWindow {
+
+  pub(crate) fn new(nb_levers : usize, window : usize) -> Self {
+    SlidingWindow {
+      window,
+      rewards : vec![VecDeque::with_capacity(window+1);nb_levers],
+      stats : vec![LeverStats::default();nb_levers],
+    }
+  }
+}
+
+impl Estimator for SlidingWindow {
+
+  fn estimate(&self, lever : usize) -> f64 {
+    let rewards = &self.rewards[lever];
+    if rewards.is_empty() {
+      0.0
+    } else {
+      rewards.iter().sum::<f64>() / (rewards.len() as f64)
+    }
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.stats[lever].add(reward);
+    self.rewards[lever].push_back(reward);
+    if self.rewards[lever].len() > self.window {
+      self.rewards[lever].pop_front();
+    }
+  }
+
+  fn reset(&mut self, lever : usize) {
+    self.stats[lever] = LeverStats::default();
+    self.rewards[lever].clear();
+  }
+
+  fn nb_levers(&self) -> usize {
+    self.rewards.len()
+  }
+
+  fn stats(&self, lever : usize) -> &LeverStats {
+    &self.stats[lever]
+  }
+
+  fn effective_count(&self, lever : usize) -> f64 {
+    self.rewards[lever].len() as f64
+  }
+
+  // Variance of the rewards in the window.
+  fn reward_variance(&self, lever : usize) -> f64 {
+    let rewards = &self.rewards[lever];
+    if rewards.len() < 2 {
+      return 0.0;
+    }
+    let mean = self.estimate(lever);
+    rewards.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / ((rewards.len() - 1) as f64)
+  }
+}
+
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct Discounted {
+  discount : f64,
+  // Discounted count, sum and sum of squares of the rewards.
+  sums : Vec<(f64,f64,f64)>,
+  stats : Vec<LeverStats>,
+}
+
+impl Discounted {
+
+  pub(crate) fn new(nb_levers : usize, discount : f64) -> Self {
+    Discounted {
+      discount,
+      sums : vec![(0.0,0.0,0.0);nb_levers],
+      stats : vec![LeverStats::default();nb_levers],
+    }
+  }
+}
+
+impl Estimator for Discounted {
+
+  fn estimate(&self, lever : usize) -> f64 {
+    let (count,sum,_) = self.sums[lever];
+    if count > 0.0 { sum / count } else { 0.0 }
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.stats[lever].add(reward);
+    for (count,sum,squares) in self.sums.iter_mut() {
+      *count *= self.discount;
+      *sum *= self.discount;
+      *squares *= self.discount;
+    }
+    let (count,sum,squares) = &mut self.sums[lever];
+    *count += 1.0;
+    *sum += reward;
+    *squares += reward*reward;
+  }
+
+  fn reset(&mut self, lever : usize) {
+    self.stats[lever] = LeverStats::default();
+    self.sums[lever] = (0.0,0.0,0.0);
+  }
+
+  fn nb_levers(&self) -> usize {
+    self.sums.len()
+  }
+
+  fn stats(&self, lever : usize) -> &LeverStats {
+    &self.stats[lever]
+  }
+
+  fn effective_count(&self, lever : usize) -> f64 {
+    self.sums[lever].0
+  }
+
+  // Discounted variance of the rewards.
+  fn reward_variance(&self, lever : usize) -> f64 {
+    let (count,_,squares) = self.sums[lever];
+    if count > 0.0 {
+      (squares / count - self.estimate(lever).powi(2)).max(0.0)
+    } else {
+      0.0
+    }
+  }
+}
+
+// Only the sum and the count of each block are kept, so the memory
+// does not grow with the number of rewards.
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct MedianOfMeans {
+  // Sum and count of the rewards of each block.
+  blocks : Vec<Vec<(f64,f64)>>,
+  // Estimates are only computed again when the lever is updated.
+  estimates : Vec<f64>,
+  stats : Vec<LeverStats>,
+}
+
+impl MedianOfMeans {
+
+  pub(crate) fn new(nb_levers : usize, nb_blocks : usize) -> Self {
+    MedianOfMeans {
+      blocks : vec![vec![(0.0,0.0);nb_blocks];nb_levers],
+      estimates : vec![0.0;nb_levers],
+      stats : vec![LeverStats::default();nb_levers],
+    }
+  }
+
+  // With less rewards than blocks, each reward is its own block.
+  fn compute(&self, lever : usize) -> f64 {
+    let means : Vec<f64> =
+      self.blocks[lever].iter()
+                        .filter(|(_,count)| *count > 0.0)
+                        .map(|(sum,count)| sum / count)
+                        .collect();
+    helper::median(&means)
+  }
+}
+
+impl Estimator for MedianOfMeans {
+
+  fn estimate(&self, lever : usize) -> f64 {
+    self.estimates[lever]
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    let nb_blocks = self.blocks[lever].len();
+    let block = &mut self.blocks[lever][self.stats[lever].count as usize % nb_blocks];
+    block.0 += reward;
+    block.1 += 1.0;
+    self.stats[lever].add(reward);
+    self.estimates[lever] = self.compute(lever);
+  }
+
+  fn reset(&mut self, lever : usize) {
+    self.stats[lever] = LeverStats::default();
+    for block in self.blocks[lever].iter_mut() {
+      *block = (0.0,0.0);
+    }
+    self.estimates[lever] = 0.0;
+  }
+
+  fn nb_levers(&self) -> usize {
+    self.blocks.len()
+  }
+
+  fn stats(&self, lever : usize) -> &LeverStats {
+    &self.stats[lever]
+  }
+}
+
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct TruncatedMean {
+  epsilon : f64,
+  moment : f64,
+  confidence : f64,
+  // Sum of the rewards kept.
+  sums : Vec<f64>,
+  stats : Vec<LeverStats>,
+}
+
+impl TruncatedMean {
+
+  pub(crate) fn new(nb_levers : usize, epsilon : f64, moment : f64, confidence : f64) -> Self {
+    TruncatedMean {
+      epsilon,
+      moment,
+      confidence,
+      sums : vec![0.0;nb_levers],
+      stats : vec![LeverStats::default();nb_levers],
+    }
+  }
+}
+
+impl Estimator for TruncatedMean {
+
+  fn estimate(&self, lever : usize) -> f64 {
+    let count = self.stats[lever].count;
+    if count > 0.0 { self.sums[lever] / count } else { 0.0 }
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.stats[lever].add(reward);
+    let threshold = (self.moment*self.stats[lever].count / (1.0/self.confidence).ln())
+                      .powf(1.0/(1.0 + self.epsilon));
+    if reward.abs() <= threshold {
+      self.sums[lever] += reward;
+    }
+  }
+
+  fn reset(&mut self, lever : usize) {
+    self.stats[lever] = LeverStats::default();
+    self.sums[lever] = 0.0;
+  }
+
+  fn nb_levers(&self) -> usize {
+    self.sums.len()
+  }
+
+  fn stats(&self, lever : usize) -> &LeverStats {
+    &self.stats[lever]
+  }
+}
+
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct Catoni {
+  variance : f64,
+  confidence : f64,
+  // Number of rewards kept, which bounds the memory and the cost
+  // of an update.
+  window : usize,
+  rewards : Vec<VecDeque<f64>>,
+  // Estimates are only computed again when the lever is updated.
+  estimates : Vec<f64>,
+  stats : Vec<LeverStats>,
+}
+
+impl Catoni {
+
+  pub(crate) fn new(nb_levers : usize, variance : f64, confidence : f64, window : usize) -> Self {
+    Catoni {
+      variance,
+      confidence,
+      window,
+      rewards : vec![VecDeque::new();nb_levers],
+      estimates : vec![0.0;nb_levers],
+      stats : vec![LeverStats::default();nb_levers],
+    }
+  }
+
+  // Root of the sum of psi(scale*(reward - mean)), which decreases
+  // with the mean, starting the search from the last estimate.
+  fn compute(&self, lever : usize) -> f64 {
+    let rewards = &self.rewards[lever];
+    let log = (1.0/self.confidence).ln();
+    let scale = (2.0*log / (rewards.len() as f64 * self.variance)).sqrt();
+    let psi = |x : f64| x.signum()*(1.0 + x.abs() + x*x/2.0).ln();
+    let last = self.estimates[lever];
+    helper::invert(|mean| -rewards.iter().map(|r| psi(scale*(r - mean))).sum::<f64>(),
+                   0.0,
+                   last - 1.0,
+                   last + 1.0)
+  }
+}
+
+impl Estimator for Catoni {
+
+  fn estimate(&self, lever : usize) -> f64 {
+    self.estimates[lever]
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.stats[lever].add(reward);
+    self.rewards[lever].push_back(reward);
+    if self.rewards[lever].len() > self.window {
+      self.rewards[lever].pop_front();
+    }
+    self.estimates[lever] = self.compute(lever);
+  }
+
+  fn reset(&mut self, lever : usize) {
+    self.stats[lever] = LeverStats::default();
+    self.rewards[lever].clear();
+    self.estimates[lever] = 0.0;
+  }
+
+  fn nb_levers(&self) -> usize {
+    self.rewards.len()
+  }
+
+  fn stats(&self, lever : usize) -> &LeverStats {
+    &self.stats[lever]
+  }
+
+  fn effective_count(&self, lever : usize) -> f64 {
+    self.rewards[lever].len() as f64
+  }
+
+  // The bound on the variance, which heavy tails cannot break like
+  // the empirical one.
+  fn reward_variance(&self, _lever : usize) -> f64 {
+    self.variance
+  }
+}
+
+// Posterior of the mean of normal rewards with known variance.
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct NormalPosterior {
+  prior : (f64,f64),
+  noise_var : f64,
+  // Mean and variance of the posterior of each lever.
+  posteriors : Vec<(f64,f64)>,
+  stats : Vec<LeverStats>,
+}
+
+impl NormalPosterior {
+
+  pub(crate) fn new(nb_levers : usize, prior_mean : f64, prior_var : f64, noise_var : f64) -> Self {
+    NormalPosterior {
+      prior : (prior_mean,prior_var),
+      noise_var,
+      posteriors : vec![(prior_mean,prior_var);nb_levers],
+      stats : vec![LeverStats::default();nb_levers],
+    }
+  }
+}
+
+impl Estimator for NormalPosterior {
+
+  fn estimate(&self, lever : usize) -> f64 {
+    self.posteriors[lever].0
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.stats[lever].add(reward);
+    let (mean,var) = self.posteriors[lever];
+    let new_var = 1.0 / (1.0/var + 1.0/self.noise_var);
+    self.posteriors[lever] = (new_var*(mean/var + reward/self.noise_var), new_var);
+  }
+
+  fn reset(&mut self, lever : usize) {
+    self.stats[lever] = LeverStats::default();
+    self.posteriors[lever] = self.prior;
+  }
+
+  fn nb_levers(&self) -> usize {
+    self.posteriors.len()
+  }
+
+  fn stats(&self, lever : usize) -> &LeverStats {
+    &self.stats[lever]
+  }
+
+  fn variance(&self, lever : usize) -> f64 {
+    self.posteriors[lever].1
+  }
+
+  fn quantile(&self, lever : usize, p : f64) -> f64 {
+    let (mean,var) = self.posteriors[lever];
+    mean + var.sqrt()*helper::normal_quantile(p)
+  }
+
+  fn sample<V: Rng>(&self, lever : usize, rng : &mut V) -> f64 {
+    let (mean,var) = self.posteriors[lever];
+    Normal::new(mean,var.sqrt()).unwrap().sample(rng)
+  }
+
+  fn upper_bound(&self, lever : usize, confidence : f64) -> f64 {
+    self.quantile(lever,confidence)
+  }
+
+  fn lower_bound(&self, lever : usize, confidence : f64) -> f64 {
+    self.quantile(lever,1.0-confidence)
+  }
+}
+
+// Parameters of a Normal-Gamma distribution.
+#[derive(Clone,Copy,Serialize,Deserialize)]
+pub(crate) struct NormalGamma {
+  mean : f64,
+  strength : f64,
+  shape : f64,
+  rate : f64,
+}
+
+// Posterior of the mean and precision of normal rewards.
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct NormalGammaPosterior {
+  prior : NormalGamma,
+  posteriors : Vec<NormalGamma>,
+  stats : Vec<LeverStats>,
+}
+
+impl NormalGammaPosterior {
+
+  pub(crate) fn new(nb_levers : usize, mean : f64, strength : f64, shape : f64, rate : f64) -> Self {
+    let prior = NormalGamma { mean, strength, shape, rate };
+    NormalGammaPosterior {
+      prior,
+      posteriors : vec![prior;nb_levers],
+      stats : vec![LeverStats::default();nb_levers],
+    }
+  }
+
+  // The mean follows a Student t distribution with 2*shape degrees
+  // of freedom, centered on the mean, and with this scale.
+  fn scale(&self, lever : usize) -> f64 {
+    let post = self.posteriors[lever];
+    (post.rate / (post.shape*post.strength)).sqrt()
+  }
+}
+
+impl Estimator for NormalGammaPosterior {
+
+  fn estimate(&self, lever : usize) -> f64 {
+    self.posteriors[lever].mean
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.stats[lever].add(reward);
+    let post = self.posteriors[lever];
+    self.posteriors[lever] = NormalGamma {
+      mean : (post.strength*post.mean + reward) / (post.strength + 1.0),
+      strength : post.strength + 1.0,
+      shape : post.shape + 0.5,
+      rate : post.rate + post.strength*(reward - post.mean).powi(2) / (2.0*(post.strength + 1.0)),
+    };
+  }
+
+  fn reset(&mut self, lever : usize) {
+    self.stats[lever] = LeverStats::default();
+    self.posteriors[lever] = self.prior;
+  }
+
+  fn nb_levers(&self) -> usize {
+    self.posteriors.len()
+  }
+
+  fn stats(&self, lever : usize) -> &LeverStats {
+    &self.stats[lever]
+  }
+
+  fn variance(&self, lever : usize) -> f64 {
+    let post = self.posteriors[lever];
+    if post.shape > 1.0 {
+      post.rate / (post.strength*(post.shape - 1.0))
+    } else {
+      f64::INFINITY
+    }
+  }
+
+  fn quantile(&self, lever : usize, p : f64) -> f64 {
+    let post = self.posteriors[lever];
+    post.mean + self.scale(lever)*helper::student_quantile(2.0*post.shape,p)
+  }
+
+  fn sample<V: Rng>(&self, lever : usize, rng : &mut V) -> f64 {
+    let post = self.posteriors[lever];
+    let precision = Gamma::new(post.shape,1.0/post.rate).unwrap().sample(rng);
+    Normal::new(post.mean,(1.0/(post.strength*precision)).sqrt()).unwrap().sample(rng)
+  }
+
+  fn upper_bound(&self, lever : usize, confidence : f64) -> f64 {
+    self.quantile(lever,confidence)
+  }
+
+  fn lower_bound(&self, lever : usize, confidence : f64) -> f64 {
+    self.quantile(lever,1.0-confidence)
+  }
+}
+
+// Posterior of the mean of Bernoulli rewards. Rewards between 0
+// and 1 count as fractional successes.
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct BetaPosterior {
+  prior : (f64,f64),
+  posteriors : Vec<(f64,f64)>,
+  stats : Vec<LeverStats>,
+}
+
+impl BetaPosterior {
+
+  pub(crate) fn new(nb_levers : usize, alpha : f64, beta : f64) -> Self {
+    BetaPosterior {
+      prior : (alpha,beta),
+      posteriors : vec![(alpha,beta);nb_levers],
+      stats : vec![LeverStats::default();nb_levers],
+    }
+  }
+}
+
+impl Estimator for BetaPosterior {
+
+  fn estimate(&self, lever : usize) -> f64 {
+    let (alpha,beta) = self.posteriors[lever];
+    alpha / (alpha + beta)
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.stats[lever].add(reward);
+    let reward = reward.clamp(0.0,1.0);
+    self.posteriors[lever].0 += reward;
+    self.posteriors[lever].1 += 1.0 - reward;
+  }
+
+  fn reset(&mut self, lever : usize) {
+    self.stats[lever] = LeverStats::default();
+    self.posteriors[lever] = self.prior;
+  }
+
+  fn nb_levers(&self) -> usize {
+    self.posteriors.len()
+  }
+
+  fn stats(&self, lever : usize) -> &LeverStats {
+    &self.stats[lever]
+  }
+
+  fn variance(&self, lever : usize) -> f64 {
+    let (alpha,beta) = self.posteriors[lever];
+    alpha*beta / ((alpha + beta).powi(2)*(alpha + beta + 1.0))
+  }
+
+  fn quantile(&self, lever : usize, p : f64) -> f64 {
+    let (alpha,beta) = self.posteriors[lever];
+    helper::beta_quantile(alpha,beta,p)
+  }
+
+  fn sample<V: Rng>(&self, lever : usize, rng : &mut V) -> f64 {
+    let (alpha,beta) = self.posteriors[lever];
+    Beta::new(alpha,beta).unwrap().sample(rng)
+  }
+
+  fn upper_bound(&self, lever : usize, confidence : f64) -> f64 {
+    self.quantile(lever,confidence)
+  }
+
+  fn lower_bound(&self, lever : usize, confidence : f64) -> f64 {
+    self.quantile(lever,1.0-confidence)
+  }
+}
+
+// Posterior of the mean of Poisson rewards.
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct GammaPosterior {
+  prior : (f64,f64),
+  // Shape and rate of the posterior of each lever.
+  posteriors : Vec<(f64,f64)>,
+  stats : Vec<LeverStats>,
+}
+
+impl GammaPosterior {
+
+  pub(crate) fn new(nb_levers : usize, shape : f64, rate : f64) -> Self {
+    GammaPosterior {
+      prior : (shape,rate),
+      posteriors : vec![(shape,rate);nb_levers],
+      stats : vec![LeverStats::default();nb_levers],
+    }
+  }
+}
+
+impl Estimator for GammaPosterior {
+
+  fn estimate(&self, lever : usize) -> f64 {
+    let (shape,rate) = self.posteriors[lever];
+    shape / rate
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.stats[lever].add(reward);
+    self.posteriors[lever].0 += reward.max(0.0);
+    self.posteriors[lever].1 += 1.0;
+  }
+
+  fn reset(&mut self, lever : usize) {
+    self.stats[lever] = LeverStats::default();
+    self.posteriors[lever] = self.prior;
+  }
+
+  fn nb_levers(&self) -> usize {
+    self.posteriors.len()
+  }
+
+  fn stats(&self, lever : usize) -> &LeverStats {
+    &self.stats[lever]
+  }
+
+  fn variance(&self, lever : usize) -> f64 {
+    let (shape,rate) = self.posteriors[lever];
+    shape / (rate*rate)
+  }
+
+  fn quantile(&self, lever : usize, p : f64) -> f64 {
+    let (shape,rate) = self.posteriors[lever];
+    helper::gamma_quantile(shape,rate,p)
+  }
+
+  fn sample<V: Rng>(&self, lever : usize, rng : &mut V) -> f64 {
+    let (shape,rate) = self.posteriors[lever];
+    Gamma::new(shape,1.0/rate).unwrap().sample(rng)
+  }
+
+  fn upper_bound(&self, lever : usize, confidence : f64) -> f64 {
+    self.quantile(lever,confidence)
+  }
+
+  fn lower_bound(&self, lever : usize, confidence : f64) -> f64 {
+    self.quantile(lever,1.0-confidence)
+  }
+}
+
+// Posterior of drifting means. The means walk at every tick of the
+// environment, however many pulls it takes. Rewards given late are
+// taken as observed at the tick they are given.
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct Kalman {
+  prior : (f64,f64),
+  walk_var : f64,
+  obs_var : f64,
+  // Mean and variance of the posterior of each lever, at the tick
+  // of its last update.
+  posteriors : Vec<(f64,f64)>,
+  updated : Vec<usize>,
+  time : usize,
+  stats : Vec<LeverStats>,
+}
+
+impl Kalman {
+
+  pub(crate) fn new(nb_levers : usize,
+                    init_mean : f64,
+                    init_var : f64,
+                    walk_var : f64,
+                    obs_var : f64) -> Self {
+    Kalman {
+      prior : (init_mean,init_var),
+      walk_var,
+      obs_var,
+      posteriors : vec![(init_mean,init_var);nb_levers],
+      updated : vec![0;nb_levers],
+      time : 0,
+      stats : vec![LeverStats::default();nb_levers],
+    }
+  }
+
+  // Posterior of the lever at the current tick, after the walk
+  // since its last update.
+  fn predicted(&self, lever : usize) -> (f64,f64) {
+    let (mean,var) = self.posteriors[lever];
+    (mean, var + self.walk_var*((self.time - self.updated[lever]) as f64))
+  }
+}
+
+impl Estimator for Kalman {
+
+  fn estimate(&self, lever : usize) -> f64 {
+    self.posteriors[lever].0
+  }
+
+  // Predict the walk of the pulled lever, then correct it.
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.stats[lever].add(reward);
+    let (mean,var) = self.predicted(lever);
+    let gain = var / (var + self.obs_var);
+    self.posteriors[lever] = (mean + gain*(reward - mean), (1.0 - gain)*var);
+    self.updated[lever] = self.time;
+  }
+
+  fn reset(&mut self, lever : usize) {
+    self.stats[lever] = LeverStats::default();
+    self.posteriors[lever] = self.prior;
+    self.updated[lever] = self.time;
+  }
+
+  fn tick(&mut self) {
+    self.time += 1;
+  }
+
+  fn nb_levers(&self) -> usize {
+    self.posteriors.len()
+  }
+
+  fn stats(&self, lever : usize) -> &LeverStats {
+    &self.stats[lever]
+  }
+
+  fn variance(&self, lever : usize) -> f64 {
+    self.predicted(lever).1
+  }
+
+  fn quantile(&self, lever : usize, p : f64) -> f64 {
+    let (mean,var) = self.predicted(lever);
+    mean + var.sqrt()*helper::normal_quantile(p)
+  }
+
+  fn sample<V: Rng>(&self, lever : usize, rng : &mut V) -> f64 {
+    let (mean,var) = self.predicted(lever);
+    Normal::new(mean,var.sqrt()).unwrap().sample(rng)
+  }
+
+  fn upper_bound(&self, lever : usize, confidence : f64) -> f64 {
+    self.quantile(lever,confidence)
+  }
+
+  fn lower_bound(&self, lever : usize, confidence : f64) -> f64 {
+    self.quantile(lever,1.0-confidence)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lever_stats() {
+        let mut est = SampleAverage::new(2,&InitialValues::default(),0.0);
+        for reward in [1.0,2.0,3.0,4.0].iter() {
+            est.update(0,*reward);
+        }
+        assert_eq!(est.count(0),4.0);
+        assert_eq!(est.estimate(0),2.5);
+        assert!((est.reward_variance(0) - 5.0/3.0).abs() < 1e-12);
+        assert!(est.lower_bound(0,0.95) < 2.5 && est.upper_bound(0,0.95) > 2.5);
+        assert_eq!(est.upper_bound(1,0.95),f64::INFINITY);
+        est.reset(0);
+        assert_eq!(est.count(0),0.0);
+    }
+
+    #[test]
+    fn test_initial_values() {
+        let mut average = SampleAverage::new(2,&InitialValues::PerLever(vec![5.0,1.0]),1.0);
+        assert_eq!(average.all(2),vec![5.0,1.0]);
+        average.update(0,1.0);
+        assert_eq!(average.estimate(0),3.0);
+
+        let mut biased = ConstantStep::new(1,0.1,&InitialValues::Uniform(5.0));
+        let mut unbiased = UnbiasedConstantStep::new(1,0.1,&InitialValues::Uniform(5.0));
+        biased.update(0,1.0);
+        unbiased.update(0,1.0);
+        assert!((biased.estimate(0) - 4.6).abs() < 1e-12);
+        assert_eq!(unbiased.estimate(0),1.0);
+        unbiased.reset(0);
+        assert_eq!(unbiased.estimate(0),5.0);
+    }
+
+    #[test]
+    fn test_step_sizes() {
+        let rewards = [1.0,2.0,3.0,4.0];
+        let init = InitialValues::default();
+        let mut power = ScheduledStep::new(1,StepSize::Power {exponent : 1.0},&init);
+        let mut kesten = ScheduledStep::new(1,StepSize::Kesten {alpha : 1.0, beta : 1.0},&init);
+        let mut harmonic = ScheduledStep::new(1,StepSize::Harmonic {scale : 2.0},&init);
+        let mut rational = ScheduledStep::new(1,StepSize::Rational {alpha : 0.5, beta : 0.0},
+                                              &InitialValues::Uniform(8.0));
+        for reward in rewards.iter() {
+            power.update(0,*reward);
+            kesten.update(0,*reward);
+            harmonic.update(0,*reward);
+            rational.update(0,*reward);
+        }
+        assert!((power.estimate(0) - 2.5).abs() < 1e-12);
+        // The errors never change sign, so the step stays at 1.
+        assert_eq!(kesten.estimate(0),4.0);
+        // Steps 1, 2/3, 1/2 and 2/5.
+        assert!((harmonic.estimate(0) - 3.0).abs() < 1e-12);
+        // A constant step of 1/2, starting from 8.
+        assert!((rational.estimate(0) - 3.5625).abs() < 1e-12);
+        rational.reset(0);
+        assert_eq!(rational.estimate(0),8.0);
+    }
+
+    #[test]
+    fn test_forgetting() {
+        let mut window = SlidingWindow::new(2,2);
+        let mut discounted = Discounted::new(2,0.5);
+        for reward in [0.0,1.0,2.0].iter() {
+            window.update(0,*reward);
+            discounted.update(0,*reward);
+        }
+        assert_eq!(window.estimate(0),1.5);
+        assert_eq!((window.count(0),window.effective_count(0)),(3.0,2.0));
+        assert_eq!(discounted.effective_count(0),1.75);
+        assert!((discounted.estimate(0) - 2.5/1.75).abs() < 1e-12);
+        discounted.update(1,0.0);
+        assert_eq!(discounted.effective_count(0),0.875);
+    }
+
+    #[test]
+    fn test_robust_estimators() {
+        let rewards = [1.0,-1.0,0.5,1000.0,0.0,-0.5];
+        let mut mom = MedianOfMeans::new(1,3);
+        let mut truncated = TruncatedMean::new(1,1.0,1.0,0.01);
+        let mut catoni = Catoni::new(1,1.0,0.01,100);
+        for reward in rewards.iter() {
+            mom.update(0,*reward);
+            truncated.update(0,*reward);
+            catoni.update(0,*reward);
+        }
+        // Block means are 500.5, -0.5 and 0.
+        assert_eq!(mom.estimate(0),0.0);
+        assert_eq!(truncated.estimate(0),0.0);
+        assert!(catoni.estimate(0).abs() < 5.0, "{}", catoni.estimate(0));
+        for _ in 0..200 {
+            mom.update(0,1.0);
+            catoni.update(0,1.0);
+        }
+        assert_eq!(mom.blocks[0].len(),3);
+        assert_eq!(catoni.rewards[0].len(),100);
+        assert_eq!(catoni.effective_count(0),100.0);
+        assert!((catoni.estimate(0) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_kalman() {
+        let mut kalman = Kalman::new(2,0.0,1.0,0.5,1.0);
+        // No walk before the first tick, so the gain is 0.5.
+        kalman.update(0,3.0);
+        assert!((kalman.estimate(0) - 1.5).abs() < 1e-12);
+        assert!((kalman.variance(0) - 0.5).abs() < 1e-12);
+        assert_eq!(kalman.variance(1),1.0);
+        // Variance 1.5 after the walk, so the gain is 0.6.
+        kalman.tick();
+        kalman.update(1,3.0);
+        assert!((kalman.estimate(1) - 1.8).abs() < 1e-12);
+        assert!((kalman.variance(1) - 0.6).abs() < 1e-12);
+        assert!((kalman.variance(0) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_conjugate_updates() {
+        let mut normal = NormalPosterior::new(1,0.0,1.0,1.0);
+        normal.update(0,2.0);
+        assert_eq!((normal.estimate(0),normal.variance(0)),(1.0,0.5));
+
+        let mut beta = BetaPosterior::new(1,1.0,1.0);
+        for reward in [1.0,1.0,0.0].iter() {
+            beta.update(0,*reward);
+        }
+        assert_eq!(beta.estimate(0),0.6);
+        let (low,high) = beta.credible_interval(0,0.9);
+        assert!(low < 0.6 && 0.6 < high);
+
+        let mut gamma = GammaPosterior::new(1,1.0,1.0);
+        gamma.update(0,3.0);
+        assert_eq!(gamma.estimate(0),2.0);
+
+        let mut normal_gamma = NormalGammaPosterior::new(1,0.0,1.0,1.0,1.0);
+        normal_gamma.update(0,2.0);
+        assert_eq!(normal_gamma.estimate(0),1.0);
+        assert!((normal_gamma.quantile(0,0.5) - 1.0).abs() < 1e-9);
+        normal_gamma.reset(0);
+        assert_eq!(normal_gamma.estimate(0),0.0);
+    }
 }
@@ -0,0 +1,260 @@
+use crate::helper;
+use crate::policies::{Policy,PolicyInit,create_policy};
+
+use std::error::Error;
+use std::fmt;
+
+use rand::Rng;
+
+// Estimate of the value of a policy, with a confidence interval
+// from the normal approximation.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct Estimate {
+  pub value : f64,
+  pub lower : f64,
+  pub upper : f64,
+}
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct OffPolicyEstimates {
+  // Inverse propensity scoring.
+  pub ips : Estimate,
+  // Self-normalised inverse propensity scoring.
+  pub snips : Estimate,
+  // Doubly robust, with the average logged reward of each lever
+  // as reward model.
+  pub doubly_robust : Estimate,
+}
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum EvaluationError {
+  // There is nothing to estimate from.
+  EmptyLog,
+  // The entry with this index has a propensity outside of (0,1].
+  InvalidPropensity(usize),
+  // The entry with this index has a lever the policy does not have.
+  UnknownLever(usize),
+  // The target policy never chooses a logged lever.
+  NoOverlap,
+}
+
+impl fmt::Display for EvaluationError {
+
+  fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      EvaluationError::EmptyLog =>
+        write!(f, "the log is empty"),
+      EvaluationError::InvalidPropensity(nb) =>
+        write!(f, "entry {}: the propensity must be in (0,1]", nb),
+      EvaluationError::UnknownLever(nb) =>
+        write!(f, "entry {}: the lever is not one of the target policy", nb),
+      EvaluationError::NoOverlap =>
+        write!(f, "the target policy never chooses a logged lever"),
+    }
+  }
+}
+
+impl Error for EvaluationError {}
+
+// Estimate the average reward of a target policy from a log of
+// (lever, reward, propensity) collected with another policy, using
+// the thread local random generator. The target policy reads the log
+// in order, and only learns from the entries it would have chosen
+// (replay method, Li et al. 2011): each entry is kept with probability
+// target/propensity, scaled by the smallest propensity of the log, so
+// the kept entries follow the target policy whatever the logging
+// policy. The confidence is the coverage of the intervals, like 0.95.
+pub fn off_policy_evaluation(log : &[(usize,f64,f64)],
+                             target : &PolicyInit,
+                             confidence : f64) -> Result<OffPolicyEstimates,EvaluationError> {
+  off_policy_evaluation_reprod(log,target,confidence,&mut rand::thread_rng())
+}
+
+// Same as off_policy_evaluation, with the given random generator for
+// the probabilities which must be estimated by sampling.
+pub fn off_policy_evaluation_reprod<T : Rng>(log : &[(usize,f64,f64)],
+                                             target : &PolicyInit,
+                                             confidence : f64,
+                                             rng : &mut T)
+                                             -> Result<OffPolicyEstimates,EvaluationError> {
+  if log.is_empty() {
+    return Err(EvaluationError::EmptyLog);
+  }
+  if let Some(nb) = log.iter().position(|&(_,_,proba)| !(proba > 0.0 && proba <= 1.0)) {
+    return Err(EvaluationError::InvalidPropensity(nb));
+  }
+  let min_proba = log.iter().map(|&(_,_,proba)| proba).fold(1.0,f64::min);
+  let mut policy = create_policy(target,Some(log.len()));
+  let mut targets = Vec::with_capacity(log.len());
+  for (nb,&(lever,reward,proba)) in log.iter().enumerate() {
+    let probas = policy.probabilities(rng);
+    if lever >= probas.len() {
+      return Err(EvaluationError::UnknownLever(nb));
+    }
+    if rng.gen_bool((probas[lever]*min_proba/proba).min(1.0)) {
+      policy.update(lever,reward);
+    }
+    policy.tick();
+    targets.push(probas);
+  }
+  estimates(log,&targets,confidence)
+}
+
+// Estimates from a checked log, with the probabilities of the target
+// policy for each entry.
+fn estimates(log : &[(usize,f64,f64)],
+             targets : &[Vec<f64>],
+             confidence : f64) -> Result<OffPolicyEstimates,EvaluationError> {
+  let nb = log.len() as f64;
+  let z = helper::normal_quantile(0.5 + confidence/2.0);
+
+  let weights : Vec<f64> =
+    log.iter()
+       .zip(targets.iter())
+       .map(|(&(lever,_,proba),target)| target[lever]/proba)
+       .collect();
+
+  let ips_terms : Vec<f64> =
+    log.iter()
+       .zip(weights.iter())
+       .map(|(&(_,reward,_),weight)| weight*reward)
+       .collect();
+  let ips = mean(&ips_terms);
+
+  let mean_weight = mean(&weights);
+  if mean_weight == 0.0 {
+    return Err(EvaluationError::NoOverlap);
+  }
+  let snips = ips/mean_weight;
+  // Delta method: the terms w(r - snips) have mean zero, and the
+  // normalisation divides their spread by the average weight.
+  let snips_terms : Vec<f64> =
+    log.iter()
+       .zip(weights.iter())
+       .map(|(&(_,reward,_),weight)| weight*(reward - snips)/mean_weight)
+       .collect();
+
+  let nb_levers = targets[0].len();
+  let mut sums = vec![0.0;nb_levers];
+  let mut counts = vec![0.0;nb_levers];
+  for &(lever,reward,_) in log {
+    sums[lever] += reward;
+    counts[lever] += 1.0;
+  }
+  let model : Vec<f64> =
+    sums.iter()
+        .zip(counts.iter())
+        .map(|(sum,count)| if *count > 0.0 { sum/count } else { 0.0 })
+        .collect();
+  let dr_terms : Vec<f64> =
+    log.iter()
+       .zip(targets.iter())
+       .zip(weights.iter())
+       .map(|((&(lever,reward,_),target),weight)| {
+         let direct : f64 = target.iter().zip(model.iter()).map(|(p,q)| p*q).sum();
+         direct + weight*(reward - model[lever])
+       })
+       .collect();
+
+  Ok(OffPolicyEstimates {
+    ips : interval(ips, std_dev(&ips_terms)/nb.sqrt(), z),
+    snips : interval(snips, std_dev(&snips_terms)/nb.sqrt(), z),
+    doubly_robust : interval(mean(&dr_terms), std_dev(&dr_terms)/nb.sqrt(), z),
+  })
+}
+
+fn interval(value : f64, std_err : f64, z : f64) -> Estimate {
+  Estimate {
+    value,
+    lower : value - z*std_err,
+    upper : value + z*std_err,
+  }
+}
+
+fn mean(vals : &[f64]) -> f64 {
+  vals.iter().sum::<f64>() / (vals.len() as f64)
+}
+
+fn std_dev(vals : &[f64]) -> f64 {
+  if vals.len() < 2 {
+    return 0.0;
+  }
+  let m = mean(vals);
+  (vals.iter().map(|x| (x-m).powi(2)).sum::<f64>() / ((vals.len()-1) as f64)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::estimators::{EstimatorInit,InitialValues};
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_on_policy_log() {
+        // Logging and target policies are both uniform, so every
+        // estimator gives back the average reward.
+        let est = EstimatorInit::SampleAverageInit {nb_levers : 2};
+        let uniform = PolicyInit::EGreedyInit {nb_levers : 2, expl_proba : 1.0.into(), est : &est};
+        let log = [(0,1.0,0.5),(1,0.0,0.5),(0,1.0,0.5),(1,1.0,0.5)];
+        let estimates = off_policy_evaluation(&log,&uniform,0.95).unwrap();
+        assert!((estimates.ips.value - 0.75).abs() < 1e-12);
+        assert!((estimates.snips.value - 0.75).abs() < 1e-12);
+        assert!((estimates.doubly_robust.value - 0.75).abs() < 1e-12);
+        assert!(estimates.ips.lower < 0.75 && estimates.ips.upper > 0.75);
+    }
+
+    #[test]
+    fn test_deterministic_target() {
+        // The prior is strong enough for the target to always
+        // choose the lever 0.
+        let est = EstimatorInit::PriorSampleAverageInit {nb_levers : 2,
+                                                         init : InitialValues::PerLever(vec![1.0,-1.0]),
+                                                         pseudo_counts : 100.0};
+        let greedy = PolicyInit::EGreedyInit {nb_levers : 2, expl_proba : 0.0.into(), est : &est};
+        let log = [(0,1.0,0.5),(1,0.0,0.5),(0,0.0,0.5),(1,0.0,0.5)];
+        let estimates = off_policy_evaluation(&log,&greedy,0.95).unwrap();
+        assert!((estimates.ips.value - 0.5).abs() < 1e-12);
+        assert!((estimates.snips.value - 0.5).abs() < 1e-12);
+        assert!((estimates.doubly_robust.value - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_invalid_logs() {
+        let est = EstimatorInit::SampleAverageInit {nb_levers : 2};
+        let policy = PolicyInit::EGreedyInit {nb_levers : 2, expl_proba : 0.5.into(), est : &est};
+        assert_eq!(off_policy_evaluation(&[],&policy,0.95),Err(EvaluationError::EmptyLog));
+        assert_eq!(off_policy_evaluation(&[(0,1.0,0.5),(1,0.0,0.0)],&policy,0.95),
+                   Err(EvaluationError::InvalidPropensity(1)));
+        assert_eq!(off_policy_evaluation(&[(2,1.0,0.5)],&policy,0.95),
+                   Err(EvaluationError::UnknownLever(0)));
+        // The target only ever chooses the lever 0, which is not logged.
+        let est = EstimatorInit::PriorSampleAverageInit {nb_levers : 2,
+                                                         init : InitialValues::PerLever(vec![1.0,-1.0]),
+                                                         pseudo_counts : 100.0};
+        let greedy = PolicyInit::EGreedyInit {nb_levers : 2, expl_proba : 0.0.into(), est : &est};
+        assert_eq!(off_policy_evaluation(&[(1,1.0,0.5),(1,0.0,0.5)],&greedy,0.95),
+                   Err(EvaluationError::NoOverlap));
+    }
+
+    #[test]
+    fn test_learning_target() {
+        // A greedy target keeps the first lever it tries: on its own it
+        // gets 1.5 on average. Learning from every logged entry would
+        // make it find the lever 1, and overestimate it near 2.
+        let est = EstimatorInit::SampleAverageInit {nb_levers : 2};
+        let greedy = PolicyInit::EGreedyInit {nb_levers : 2, expl_proba : 0.0.into(), est : &est};
+        let mut rng = StdRng::seed_from_u64(0);
+        let nb_logs = 400;
+        let average = (0..nb_logs).map(|_| {
+          let log : Vec<(usize,f64,f64)> =
+            (0..100).map(|_| {
+                       let lever = rng.gen_range(0,2);
+                       (lever, (lever+1) as f64, 0.5)
+                     })
+                     .collect();
+          off_policy_evaluation_reprod(&log,&greedy,0.95,&mut rng).unwrap().ips.value
+        }).sum::<f64>() / (nb_logs as f64);
+        assert!((average - 1.5).abs() < 0.1, "{}", average);
+    }
+}
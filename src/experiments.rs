@@ -1,44 +1,247 @@
 use rand::Rng;
+use serde::{Serialize,Deserialize};
 
-use crate::problems::Bandit;
-use crate::problems::BanditEnum;
-use crate::policies::Policy;
-use crate::policies::PolicyEnum;
+use crate::helper;
 
+use crate::problems::{Bandit,BanditInit,BanditEnum,create_bandit};
+use crate::policies::{Policy,PolicyInit,PolicyEnum,create_policy};
+use crate::snapshot::ExperimentSnapshot;
 
-#[derive(Clone)]
-pub(crate) struct Experiment {
+
+// Timing of the interactions between the policy and the environment.
+// Every round, the policy pulls pulls_per_tick levers, then the
+// environment ticks ticks_per_round times. The rewards are given to
+// the policy by batches of batch_size pulls.
+#[derive(Clone,Copy,Debug,PartialEq,Serialize,Deserialize)]
+pub struct ClockInit {
+  pub pulls_per_tick : usize,
+  pub ticks_per_round : usize,
+  pub batch_size : usize,
+}
+
+// One pull per tick, and rewards given right away.
+impl Default for ClockInit {
+
+  fn default() -> Self {
+    ClockInit {
+      pulls_per_tick : 1,
+      ticks_per_round : 1,
+      batch_size : 1,
+    }
+  }
+}
+
+// A policy facing a simulated problem. The experiment can be run by
+// parts, and saved in between to resume it later.
+#[derive(Clone,Serialize,Deserialize)]
+pub struct Experiment {
   policy : PolicyEnum,
   problem : BanditEnum,
+  clock : ClockInit,
   results : Vec<Step>,
+  // Steps whose reward was not given to the policy yet.
+  batch : Vec<usize>,
+  // Pulls since the last tick.
+  pulls : usize,
+  // Whether the levers changed abruptly since the last step.
+  changepoint : bool,
+  // Whether to record probabilities the policy can only estimate.
+  record : bool,
 }
 
 impl Experiment {
 
   pub(crate) fn new(policy : PolicyEnum,
-             problem : BanditEnum) -> Self {
+             problem : BanditEnum,
+             clock : ClockInit) -> Self {
     Experiment {
+      changepoint : problem.is_changepoint(),
       problem,
       policy,
+      clock,
       results : Vec::new(),
+      batch : Vec::with_capacity(clock.batch_size),
+      pulls : 0,
+      record : false,
     }
   }
 
-  pub(crate) fn steps<V: Rng>(mut self, steps : usize, rng : &mut V) -> Vec<Step> {
-    for _ in 0..steps {
+  // Record the probabilities of the policy at every step, even when
+  // it can only estimate them by sampling decisions, which is slow.
+  // Otherwise, they are only recorded when they are exact.
+  pub fn record_probabilities(mut self, record : bool) -> Self {
+    self.record = record;
+    self
+  }
+
+  // Experiment of len_exp steps, with a problem sampled with rng.
+  pub fn from_init<T : Rng>(policy : &PolicyInit,
+                            problem : &BanditInit,
+                            clock : ClockInit,
+                            len_exp : usize,
+                            rng : &mut T) -> Self {
+    Experiment::new(create_policy(policy,Some(len_exp)),create_bandit(problem,rng),clock)
+  }
+
+  // Run the experiment until it has the required number of steps
+  // in total, or the problem is exhausted. Rewards of an incomplete
+  // batch stay pending.
+  pub fn run<V: Rng>(&mut self, steps : usize, rng : &mut V) {
+    while self.results.len() < steps && !self.problem.is_exhausted() {
+      let probas = if self.record || self.policy.exact_probabilities() {
+        Some(self.policy.probabilities(rng))
+      } else {
+        None
+      };
       let lever = self.policy.decide(rng);
+      if !self.problem.accepts(lever) {
+        continue;
+      }
       let optimal = self.problem.is_optimal(lever);
       let reward = self.problem.use_lever(lever,rng);
-      self.policy.update(lever,reward);
-      self.results.push(Step { lever, optimal, reward, });
+      self.results.push(Step { lever,
+                               optimal,
+                               reward,
+                               propensity : probas.as_ref().map(|probas| probas[lever]),
+                               entropy : probas.as_ref().map(|probas| helper::entropy(probas)),
+                               changepoint : self.changepoint,
+                               alarm : false, });
+      self.changepoint = false;
+      self.batch.push(self.results.len()-1);
+      if self.batch.len() >= self.clock.batch_size {
+        self.give_rewards();
+      }
+      self.pulls += 1;
+      if self.pulls >= self.clock.pulls_per_tick {
+        self.pulls = 0;
+        for _ in 0..self.clock.ticks_per_round {
+          self.problem.tick(rng);
+          self.policy.tick();
+          self.changepoint |= self.problem.is_changepoint();
+        }
+      }
     }
+  }
+
+  // Steps made so far.
+  pub fn results(&self) -> &[Step] {
+    &self.results
+  }
+
+  // End the experiment, giving the pending rewards to the policy.
+  pub fn finish(mut self) -> Vec<Step> {
+    self.give_rewards();
     self.results
   }
+
+  // Save the state of the experiment, to resume it later.
+  pub fn snapshot(&self) -> ExperimentSnapshot {
+    ExperimentSnapshot::new(self.clone())
+  }
+
+  pub fn restore(snapshot : ExperimentSnapshot) -> Self {
+    snapshot.experiment
+  }
+
+  fn give_rewards(&mut self) {
+    for nb in self.batch.drain(..) {
+      self.policy.update(self.results[nb].lever,self.results[nb].reward);
+      self.results[nb].alarm = self.policy.alarm();
+    }
+  }
+}
+
+// Experiments run by the same runners, with or without contexts.
+pub(crate) trait Runnable : Send {
+  // Run the experiment for the required number of steps, or until
+  // the problem is exhausted, and give all the rewards.
+  fn steps<V: Rng>(self, steps : usize, rng : &mut V) -> Vec<Step>;
+}
+
+impl Runnable for Experiment {
+
+  fn steps<V: Rng>(mut self, steps : usize, rng : &mut V) -> Vec<Step> {
+    self.run(steps,rng);
+    self.finish()
+  }
 }
 
-#[derive(Clone)]
+#[derive(Clone,Serialize,Deserialize)]
 pub struct Step {
   pub lever : usize,
   pub optimal : bool,
   pub reward : f64,
+  // Probability the policy gave to the chosen lever, and entropy of
+  // its distribution over levers. None when they were not recorded.
+  pub propensity : Option<f64>,
+  pub entropy : Option<f64>,
+  // Whether the levers changed abruptly since the previous step.
+  pub changepoint : bool,
+  // Whether the policy detected a change after this step.
+  pub alarm : bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Changepoints,EstimatorInit};
+    use crate::estimators::Estimator;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_clock() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let est = EstimatorInit::SampleAverageInit {nb_levers : 2};
+        let policy = PolicyInit::EGreedyInit {nb_levers : 2, expl_proba : 0.1.into(), est : &est};
+        let problem = BanditInit::PiecewiseStationaryInit {nb_levers : 2,
+                                                           init_vals : (0.0,1.0),
+                                                           changes : Changepoints::Fixed(vec![6])};
+        let clock = ClockInit { pulls_per_tick : 2, ticks_per_round : 3, batch_size : 4 };
+        let exp = Experiment::new(create_policy(&policy,None),create_bandit(&problem,&mut rng),clock);
+        let steps = exp.steps(10,&mut rng);
+        // Tick 6 happens after the second round, so before step 4.
+        assert_eq!(crate::changepoints(&steps),vec![4]);
+    }
+
+    #[test]
+    fn test_recorded_probabilities() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let est = EstimatorInit::SampleAverageInit {nb_levers : 2};
+        let egreedy = PolicyInit::EGreedyInit {nb_levers : 2, expl_proba : 0.1.into(), est : &est};
+        let thompson = PolicyInit::ThompsonInit {nb_levers : 2, est : &est};
+        let problem = BanditInit::StationaryInit {nb_levers : 2, init_vals : (0.0,1.0)};
+        let clock = ClockInit::default();
+        let steps = Experiment::from_init(&egreedy,&problem,clock,10,&mut rng).steps(10,&mut rng);
+        assert!(steps.iter().all(|step| step.propensity.is_some() && step.entropy.is_some()));
+        let steps = Experiment::from_init(&thompson,&problem,clock,10,&mut rng).steps(10,&mut rng);
+        assert!(steps.iter().all(|step| step.propensity.is_none() && step.entropy.is_none()));
+        let steps = Experiment::from_init(&thompson,&problem,clock,10,&mut rng).record_probabilities(true)
+                                                                               .steps(10,&mut rng);
+        assert!(steps.iter().all(|step| step.propensity.is_some()));
+    }
+
+    #[test]
+    fn test_kalman_walks_per_tick() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let est = EstimatorInit::KalmanInit {nb_levers : 1,
+                                             init_mean : 0.0,
+                                             init_var : 1.0,
+                                             walk_var : 0.1,
+                                             obs_var : 1.0};
+        let policy = PolicyInit::ThompsonInit {nb_levers : 1, est : &est};
+        let problem = BanditInit::StationaryInit {nb_levers : 1, init_vals : (0.0,1.0)};
+        let clock = ClockInit { pulls_per_tick : 2, ticks_per_round : 1, batch_size : 1 };
+        let mut exp = Experiment::from_init(&policy,&problem,clock,10,&mut rng);
+        exp.run(10,&mut rng);
+        // Two corrections per tick, and the walk of the last tick.
+        let mut var = 1.0;
+        for _ in 0..5 {
+            for _ in 0..2 {
+                var = var/(var + 1.0);
+            }
+            var += 0.1;
+        }
+        assert!((exp.policy.estimator().variance(0) - var).abs() < 1e-12);
+    }
 }
@@ -6,7 +6,7 @@ pub fn indices_max(vals : &[f64]) -> Vec<usize> {
         .enumerate()
         .fold((vals[0],Vec::new()),
               |(mut max,mut occs), (nb,est)| {
-                if (*est-max).abs() <= std::f64::EPSILON {
+                if *est == max || (*est-max).abs() <= f64::EPSILON {
                   occs.push(nb);
                 } else if *est > max {
                   max = *est;
@@ -19,6 +19,275 @@ pub fn indices_max(vals : &[f64]) -> Vec<usize> {
   }
 }
 
+pub fn dot(x : &[f64], y : &[f64]) -> f64 {
+  x.iter().zip(y.iter()).map(|(a,b)| a*b).sum()
+}
+
+// Distribution giving the same probability to each of the levers,
+// and none to the others.
+pub fn uniform_over(levers : &[usize], nb_levers : usize) -> Vec<f64> {
+  let mut probas = vec![0.0;nb_levers];
+  for lever in levers {
+    probas[*lever] = 1.0 / (levers.len() as f64);
+  }
+  probas
+}
+
+// Entropy (in nats) of a probability distribution.
+pub fn entropy(probas : &[f64]) -> f64 {
+  -probas.iter()
+         .filter(|p| **p > 0.0)
+         .map(|p| p*p.ln())
+         .sum::<f64>()
+}
+
+// Kullback-Leibler divergence between Bernoulli distributions.
+pub fn kl_bernoulli(p : f64, q : f64) -> f64 {
+  const EPS : f64 = 1e-15;
+  let p = p.clamp(EPS,1.0-EPS);
+  let q = q.clamp(EPS,1.0-EPS);
+  p*(p/q).ln() + (1.0-p)*((1.0-p)/(1.0-q)).ln()
+}
+
+// Kullback-Leibler divergence between Poisson distributions.
+pub fn kl_poisson(p : f64, q : f64) -> f64 {
+  const EPS : f64 = 1e-15;
+  let q = q.max(EPS);
+  if p <= 0.0 {
+    q
+  } else {
+    q - p + p*(p/q).ln()
+  }
+}
+
+// Largest q in [mean,upper] such that kl(mean,q) <= bound, found by
+// bisection. The divergence must increase with q above mean.
+pub fn kl_ucb<F : Fn(f64,f64) -> f64>(mean : f64, bound : f64, kl : F, upper : f64) -> f64 {
+  const PRECISION : f64 = 1e-6;
+  let (mut low, mut high) = (mean, upper);
+  while high - low > PRECISION {
+    let mid = (low + high) / 2.0;
+    if kl(mean,mid) > bound {
+      high = mid;
+    } else {
+      low = mid;
+    }
+  }
+  (low + high) / 2.0
+}
+
+// Quantile function of the standard normal distribution, with the
+// rational approximation of Acklam (relative error below 1.2e-9).
+pub fn normal_quantile(p : f64) -> f64 {
+  const A : [f64;6] = [-3.969_683_028_665_376e1, 2.209_460_984_245_205e2,
+                       -2.759_285_104_469_687e2, 1.383_577_518_672_69e2,
+                       -3.066_479_806_614_716e1, 2.506_628_277_459_239];
+  const B : [f64;5] = [-5.447_609_879_822_406e1, 1.615_858_368_580_409e2,
+                       -1.556_989_798_598_866e2, 6.680_131_188_771_972e1,
+                       -1.328_068_155_288_572e1];
+  const C : [f64;6] = [-7.784_894_002_430_293e-3, -3.223_964_580_411_365e-1,
+                       -2.400_758_277_161_838, -2.549_732_539_343_734,
+                       4.374_664_141_464_968, 2.938_163_982_698_783];
+  const D : [f64;4] = [7.784_695_709_041_462e-3, 3.224_671_290_700_398e-1,
+                       2.445_134_137_142_996, 3.754_408_661_907_416];
+  const LOW : f64 = 0.02425;
+
+  if p <= 0.0 {
+    f64::NEG_INFINITY
+  } else if p >= 1.0 {
+    f64::INFINITY
+  } else if p < LOW {
+    let q = (-2.0*p.ln()).sqrt();
+    (((((C[0]*q+C[1])*q+C[2])*q+C[3])*q+C[4])*q+C[5]) /
+      ((((D[0]*q+D[1])*q+D[2])*q+D[3])*q+1.0)
+  } else if p <= 1.0 - LOW {
+    let q = p - 0.5;
+    let r = q*q;
+    (((((A[0]*r+A[1])*r+A[2])*r+A[3])*r+A[4])*r+A[5])*q /
+      (((((B[0]*r+B[1])*r+B[2])*r+B[3])*r+B[4])*r+1.0)
+  } else {
+    -normal_quantile(1.0 - p)
+  }
+}
+
+// Logarithm of the gamma function, with the Lanczos approximation.
+pub fn ln_gamma(x : f64) -> f64 {
+  const COEFS : [f64;6] = [76.180_091_729_471_46, -86.505_320_329_416_77,
+                           24.014_098_240_830_91, -1.231_739_572_450_155,
+                           0.001_208_650_973_866_179, -0.000_005_395_239_384_953];
+  let tmp = x + 5.5;
+  let series = COEFS.iter()
+                    .enumerate()
+                    .fold(1.000_000_000_190_015, |acc,(nb,c)| acc + c/(x + 1.0 + nb as f64));
+  (2.506_628_274_631_000_5*series/x).ln() + (x+0.5)*tmp.ln() - tmp
+}
+
+// Regularized incomplete beta function I_x(a,b), by its continued
+// fraction (Numerical Recipes, 6.4).
+pub fn inc_beta(a : f64, b : f64, x : f64) -> f64 {
+  if x <= 0.0 {
+    return 0.0;
+  } else if x >= 1.0 {
+    return 1.0;
+  }
+  let front = (ln_gamma(a+b) - ln_gamma(a) - ln_gamma(b) + a*x.ln() + b*(1.0-x).ln()).exp();
+  if x < (a+1.0)/(a+b+2.0) {
+    front*beta_fraction(a,b,x)/a
+  } else {
+    1.0 - front*beta_fraction(b,a,1.0-x)/b
+  }
+}
+
+fn beta_fraction(a : f64, b : f64, x : f64) -> f64 {
+  const TINY : f64 = 1e-300;
+  let mut c = 1.0;
+  let mut d = 1.0 - (a+b)*x/(a+1.0);
+  d = 1.0 / if d.abs() < TINY { TINY } else { d };
+  let mut result = d;
+  for m in 1..300 {
+    let m = m as f64;
+    for num in [m*(b-m)*x/((a+2.0*m-1.0)*(a+2.0*m)),
+                -(a+m)*(a+b+m)*x/((a+2.0*m)*(a+2.0*m+1.0))].iter() {
+      d = 1.0 + num*d;
+      d = 1.0 / if d.abs() < TINY { TINY } else { d };
+      c = 1.0 + num/c;
+      if c.abs() < TINY {
+        c = TINY;
+      }
+      result *= d*c;
+    }
+    if (d*c - 1.0).abs() < 1e-14 {
+      break;
+    }
+  }
+  result
+}
+
+// Regularized lower incomplete gamma function P(a,x), by its series
+// or its continued fraction (Numerical Recipes, 6.2).
+pub fn inc_gamma(a : f64, x : f64) -> f64 {
+  if x <= 0.0 {
+    return 0.0;
+  }
+  let front = (-x + a*x.ln() - ln_gamma(a)).exp();
+  if x < a + 1.0 {
+    let mut term = 1.0/a;
+    let mut sum = term;
+    for nb in 1..1000 {
+      term *= x/(a + nb as f64);
+      sum += term;
+      if term.abs() < sum.abs()*1e-15 {
+        break;
+      }
+    }
+    sum*front
+  } else {
+    const TINY : f64 = 1e-300;
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0/TINY;
+    let mut d = 1.0/b;
+    let mut result = d;
+    for nb in 1..1000 {
+      let an = -(nb as f64)*(nb as f64 - a);
+      b += 2.0;
+      d = an*d + b;
+      d = 1.0 / if d.abs() < TINY { TINY } else { d };
+      c = b + an/c;
+      if c.abs() < TINY {
+        c = TINY;
+      }
+      result *= d*c;
+      if (d*c - 1.0).abs() < 1e-14 {
+        break;
+      }
+    }
+    1.0 - front*result
+  }
+}
+
+// Softmax of the values at this temperature, computed with the
+// log-sum-exp trick so that large values do not overflow. A zero
+// temperature, or infinite maxima, give the uniform distribution
+// over the maxima.
+pub fn softmax(vals : &[f64], temperature : f64) -> Vec<f64> {
+  let max = vals.iter().cloned().fold(f64::NEG_INFINITY,f64::max);
+  if temperature <= 0.0 || max.is_infinite() {
+    return uniform_over(&indices_max(vals),vals.len());
+  }
+  let log_sum = max + vals.iter()
+                          .map(|x| ((x - max)/temperature).exp())
+                          .sum::<f64>()
+                          .ln()*temperature;
+  vals.iter()
+      .map(|x| ((x - log_sum)/temperature).exp())
+      .collect()
+}
+
+// Median of the values, zero when there are none.
+#[allow(clippy::manual_is_multiple_of)]
+pub fn median(vals : &[f64]) -> f64 {
+  if vals.is_empty() {
+    return 0.0;
+  }
+  let mut sorted = vals.to_vec();
+  sorted.sort_by(|a,b| a.total_cmp(b));
+  let mid = sorted.len() / 2;
+  if sorted.len() % 2 == 0 {
+    (sorted[mid-1] + sorted[mid]) / 2.0
+  } else {
+    sorted[mid]
+  }
+}
+
+// Invert an increasing function by bisection, after widening the
+// bracket [low,high] until it contains the solution.
+pub fn invert<F : Fn(f64) -> f64>(f : F, target : f64, mut low : f64, mut high : f64) -> f64 {
+  while f(low) > target {
+    low -= 2.0*(high - low).abs().max(1.0);
+  }
+  while f(high) < target {
+    high += 2.0*(high - low).abs().max(1.0);
+  }
+  for _ in 0..200 {
+    let mid = (low + high) / 2.0;
+    if f(mid) < target {
+      low = mid;
+    } else {
+      high = mid;
+    }
+    if high - low <= 1e-12*high.abs().max(1.0) {
+      break;
+    }
+  }
+  (low + high) / 2.0
+}
+
+// Quantile of order p of the Beta(a,b) distribution.
+pub fn beta_quantile(a : f64, b : f64, p : f64) -> f64 {
+  invert(|x| inc_beta(a,b,x), p, 0.0, 1.0)
+}
+
+// Quantile of order p of the Gamma distribution with this shape
+// and rate.
+pub fn gamma_quantile(shape : f64, rate : f64, p : f64) -> f64 {
+  invert(|x| inc_gamma(shape,x), p, 0.0, shape.max(1.0)) / rate
+}
+
+// Quantile of order p of the Student t distribution. Near 0,
+// dof/(dof + t*t) rounds to 1, so the tail is computed from its
+// complement there.
+pub fn student_quantile(dof : f64, p : f64) -> f64 {
+  let cdf = |t : f64| {
+    let tail = if t*t < dof {
+      0.5 - 0.5*inc_beta(0.5, dof/2.0, t*t/(dof + t*t))
+    } else {
+      0.5*inc_beta(dof/2.0, 0.5, dof/(dof + t*t))
+    };
+    if t >= 0.0 { 1.0 - tail } else { tail }
+  };
+  invert(cdf, p, -1.0, 1.0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -27,4 +296,30 @@ mod tests {
     fn test_indices_max() {
         assert_eq!(indices_max(&(vec![0.0,1.0])[..]),vec![1]);
     }
+
+    #[test]
+    fn test_softmax() {
+        let probas = softmax(&[0.0,2.0_f64.ln()],1.0);
+        assert!((probas[0] - 1.0/3.0).abs() < 1e-12 && (probas[1] - 2.0/3.0).abs() < 1e-12);
+        assert_eq!(softmax(&[f64::INFINITY,0.0,f64::INFINITY],1.0),vec![0.5,0.0,0.5]);
+        assert_eq!(softmax(&[f64::NEG_INFINITY,f64::NEG_INFINITY],1.0),vec![0.5,0.5]);
+    }
+
+    #[test]
+    fn test_normal_quantile() {
+        assert!(normal_quantile(0.5).abs() < 1e-9);
+        assert!((normal_quantile(0.975) - 1.959_963_985).abs() < 1e-8);
+        assert!((normal_quantile(0.001) + 3.090_232_306).abs() < 1e-8);
+    }
+
+    #[test]
+    fn test_quantiles() {
+        assert!((beta_quantile(1.0,1.0,0.3) - 0.3).abs() < 1e-9);
+        assert!((beta_quantile(2.0,5.0,0.5) - 0.264_449_983).abs() < 1e-7);
+        assert!((gamma_quantile(1.0,2.0,0.5) - 2.0_f64.ln()/2.0).abs() < 1e-9);
+        assert!((gamma_quantile(3.0,1.0,0.95) - 6.295_793_622).abs() < 1e-7);
+        assert!((student_quantile(5.0,0.975) - 2.570_581_836).abs() < 1e-7);
+        assert!((student_quantile(1.0,0.25) + 1.0).abs() < 1e-9);
+        assert!(student_quantile(3.0,0.5).abs() < 1e-12);
+    }
 }
@@ -0,0 +1,600 @@
+// Best-arm identification: instead of maximizing the rewards over a
+// fixed number of pulls, the algorithms look for the best lever, and
+// stop once they have found it with confidence 1 - confidence (fixed
+// confidence), or when their budget of pulls is spent (fixed budget).
+// The problems are assumed stationary: the environment never ticks.
+
+use crate::estimators::{Estimator,InitialValues,SampleAverage};
+use crate::helper;
+use crate::problems::{Bandit,BanditInit,BanditEnum,create_bandit};
+
+use std::error::Error;
+use std::fmt;
+use std::iter::once;
+
+use rand::Rng;
+use rand_distr::{Distribution,Normal};
+use rayon::prelude::*;
+use enum_dispatch::enum_dispatch;
+
+#[derive(Clone)]
+pub enum IdentificationInit {
+  // LUCB1, for rewards in an interval of length range: pull the
+  // empirical best lever and its strongest challenger, until their
+  // confidence intervals separate.
+  LUCBInit {nb_levers : usize,
+            range : f64,
+            confidence : f64},
+  // Track-and-Stop with D-tracking of the optimal proportions, for
+  // normal rewards of known variance.
+  TrackAndStopInit {nb_levers : usize,
+                    variance : f64,
+                    confidence : f64},
+  // Top-Two Thompson sampling, for normal rewards of known variance:
+  // the leader of a posterior sample is pulled with probability
+  // leader_proba, otherwise the leader of a sample it loses.
+  TopTwoThompsonInit {nb_levers : usize,
+                      variance : f64,
+                      leader_proba : f64,
+                      confidence : f64},
+  // Successive Halving with a fixed budget of pulls: the levers are
+  // pulled equally in each round, and the worst half is discarded.
+  // The budget must cover one pull of each lever in each of the
+  // ceil(log2(nb_levers)) rounds.
+  SuccessiveHalvingInit {nb_levers : usize,
+                         budget : usize},
+}
+
+// Outcome of the tries of an identification algorithm.
+#[derive(Clone,Debug,PartialEq)]
+pub struct IdentificationResults {
+  // Lever recommended by each try.
+  pub recommended : Vec<usize>,
+  // Number of pulls made by each try.
+  pub samples : Vec<usize>,
+  // Average number of pulls.
+  pub sample_complexity : f64,
+  // Fraction of the tries recommending a lever which is not optimal.
+  pub error_rate : f64,
+  // Number of tries stopped by max_pulls instead of the algorithm.
+  pub truncated : usize,
+}
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum IdentificationError {
+  // There is no lever to identify.
+  NoLever,
+  // The budget of Successive Halving does not allow one pull of each
+  // lever in each of this number of rounds.
+  SmallBudget(usize),
+}
+
+impl fmt::Display for IdentificationError {
+
+  fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      IdentificationError::NoLever =>
+        write!(f, "identification needs at least one lever"),
+      IdentificationError::SmallBudget(nb_rounds) =>
+        write!(f, "the budget must allow one pull of each lever in each of the {} rounds", nb_rounds),
+    }
+  }
+}
+
+impl Error for IdentificationError {}
+
+pub fn run_identification(algorithms : &[IdentificationInit],
+                          problem : BanditInit,
+                          nb_tries : usize,
+                          max_pulls : usize)
+                          -> Result<Vec<IdentificationResults>,IdentificationError> {
+  let identifiers = algorithms.iter()
+                              .map(create_identifier)
+                              .collect::<Result<Vec<IdentifierEnum>,IdentificationError>>()?;
+  Ok(identifiers.par_iter()
+                .map(|identifier|
+                  once(identifier).cycle()
+                                  .take(nb_tries)
+                                  .collect::<Vec<&IdentifierEnum>>()
+                                  .into_par_iter()
+                                  .map(|identifier| {
+                                    let mut rng = rand::thread_rng();
+                                    identify(identifier.clone(),create_bandit(&problem,&mut rng),
+                                             max_pulls,&mut rng)
+                                  })
+                                  .collect::<Vec<(usize,usize,bool,bool)>>()
+                )
+                .map(|tries| summarize(&tries))
+                .collect())
+}
+
+pub fn run_reprod_identification<T>(algorithms : &[IdentificationInit],
+                                    problem : BanditInit,
+                                    rng : &mut T,
+                                    nb_tries : usize,
+                                    max_pulls : usize)
+                                    -> Result<Vec<IdentificationResults>,IdentificationError>
+  where T : Rng {
+  let identifiers = algorithms.iter()
+                              .map(create_identifier)
+                              .collect::<Result<Vec<IdentifierEnum>,IdentificationError>>()?;
+  Ok(identifiers.iter()
+                .map(|identifier|
+                  (0..nb_tries).map(|_| identify(identifier.clone(),create_bandit(&problem,rng),
+                                                 max_pulls,rng))
+                               .collect::<Vec<(usize,usize,bool,bool)>>()
+                )
+                .map(|tries| summarize(&tries))
+                .collect())
+}
+
+// Run one try on the bandit, and give the recommended lever, the
+// number of pulls, whether the lever is optimal and whether the try
+// needed more than max_pulls.
+fn identify<T : Rng>(mut identifier : IdentifierEnum,
+                     mut bandit : BanditEnum,
+                     max_pulls : usize,
+                     rng : &mut T) -> (usize,usize,bool,bool) {
+  let mut pulls = 0;
+  let mut truncated = false;
+  // A try which stops right at max_pulls is not truncated.
+  while let Some(lever) = identifier.next(rng) {
+    if pulls >= max_pulls {
+      truncated = true;
+      break;
+    }
+    let reward = bandit.use_lever(lever,rng);
+    identifier.update(lever,reward);
+    pulls += 1;
+  }
+  let lever = identifier.recommend();
+  (lever,pulls,bandit.is_optimal(lever),truncated)
+}
+
+fn summarize(tries : &[(usize,usize,bool,bool)]) -> IdentificationResults {
+  let nb = tries.len().max(1) as f64;
+  IdentificationResults {
+    recommended : tries.iter().map(|x| x.0).collect(),
+    samples : tries.iter().map(|x| x.1).collect(),
+    sample_complexity : tries.iter().map(|x| x.1 as f64).sum::<f64>() / nb,
+    error_rate : tries.iter().filter(|x| !x.2).count() as f64 / nb,
+    truncated : tries.iter().filter(|x| x.3).count(),
+  }
+}
+
+pub(crate) fn create_identifier(init_data : &IdentificationInit)
+                                -> Result<IdentifierEnum,IdentificationError> {
+  Ok(match *init_data {
+    IdentificationInit::LUCBInit {nb_levers,range,confidence} =>
+      LUCB::new(nb_levers,range,confidence)?.into(),
+    IdentificationInit::TrackAndStopInit {nb_levers,variance,confidence} =>
+      TrackAndStop::new(nb_levers,variance,confidence)?.into(),
+    IdentificationInit::TopTwoThompsonInit {nb_levers,variance,leader_proba,confidence} =>
+      TopTwoThompson::new(nb_levers,variance,leader_proba,confidence)?.into(),
+    IdentificationInit::SuccessiveHalvingInit {nb_levers,budget} =>
+      SuccessiveHalving::new(nb_levers,budget)?.into(),
+  })
+}
+
+#[enum_dispatch]
+#[derive(Clone)]
+pub(crate) enum IdentifierEnum {
+  LUCB,
+  TrackAndStop,
+  TopTwoThompson,
+  SuccessiveHalving,
+}
+
+#[enum_dispatch(IdentifierEnum)]
+pub(crate) trait Identifier : Clone + Send {
+  // Lever to pull next, or None once the algorithm stops.
+  fn next<V: Rng>(&mut self, rng : &mut V) -> Option<usize>;
+
+  fn update(&mut self, lever : usize, reward : f64);
+
+  // Lever believed to be the best.
+  fn recommend(&self) -> usize;
+}
+
+fn sample_average(nb_levers : usize) -> Result<SampleAverage,IdentificationError> {
+  if nb_levers == 0 {
+    return Err(IdentificationError::NoLever);
+  }
+  Ok(SampleAverage::new(nb_levers,&InitialValues::default(),0.0))
+}
+
+// Lever never pulled, if any.
+fn untried(estimator : &SampleAverage) -> Option<usize> {
+  (0..estimator.nb_levers()).find(|lever| estimator.count(*lever) == 0.0)
+}
+
+fn best(estimator : &SampleAverage) -> usize {
+  helper::indices_max(&estimator.all(estimator.nb_levers()))[0]
+}
+
+// Generalized likelihood ratio statistic of the empirical best lever
+// against its closest competitor, for normal rewards.
+fn gaussian_glr(estimator : &SampleAverage, variance : f64) -> f64 {
+  let leader = best(estimator);
+  (0..estimator.nb_levers()).filter(|lever| *lever != leader)
+                            .map(|lever| {
+                              let gap = estimator.estimate(leader) - estimator.estimate(lever);
+                              gap*gap / (2.0*variance*(1.0/estimator.count(leader)
+                                                       + 1.0/estimator.count(lever)))
+                            })
+                            .fold(f64::INFINITY,f64::min)
+}
+
+// Threshold of the GLR stopping rule used in the experiments of
+// Garivier and Kaufmann (2016).
+fn glr_threshold(time : f64, confidence : f64) -> f64 {
+  ((time.ln() + 1.0) / confidence).ln()
+}
+
+#[derive(Clone)]
+pub(crate) struct LUCB {
+  range : f64,
+  confidence : f64,
+  time : usize,
+  // Levers still to pull in this round.
+  queue : Vec<usize>,
+  estimator : SampleAverage,
+}
+
+impl LUCB {
+
+  pub(crate) fn new(nb_levers : usize, range : f64, confidence : f64) -> Result<Self,IdentificationError> {
+    Ok(LUCB {
+      range,
+      confidence,
+      time : 0,
+      queue : (0..nb_levers).rev().collect(),
+      estimator : sample_average(nb_levers)?,
+    })
+  }
+
+  fn radius(&self, lever : usize) -> f64 {
+    let nb_levers = self.estimator.nb_levers() as f64;
+    let time = self.time.max(1) as f64;
+    let log = (5.0*nb_levers*time.powi(4) / (4.0*self.confidence)).ln();
+    self.range*(log / (2.0*self.estimator.count(lever))).sqrt()
+  }
+}
+
+impl Identifier for LUCB {
+
+  fn next<V: Rng>(&mut self, _rng : &mut V) -> Option<usize> {
+    if self.queue.is_empty() {
+      let leader = best(&self.estimator);
+      let challenger = (0..self.estimator.nb_levers())
+                         .filter(|lever| *lever != leader)
+                         .max_by(|a,b| (self.estimator.estimate(*a) + self.radius(*a))
+                                         .total_cmp(&(self.estimator.estimate(*b) + self.radius(*b))))?;
+      if self.estimator.estimate(leader) - self.radius(leader)
+           > self.estimator.estimate(challenger) + self.radius(challenger) {
+        return None;
+      }
+      self.queue = vec![challenger,leader];
+    }
+    self.queue.pop()
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.time += 1;
+    self.estimator.update(lever,reward);
+  }
+
+  fn recommend(&self) -> usize {
+    best(&self.estimator)
+  }
+}
+
+#[derive(Clone)]
+pub(crate) struct TrackAndStop {
+  variance : f64,
+  confidence : f64,
+  estimator : SampleAverage,
+}
+
+impl TrackAndStop {
+
+  pub(crate) fn new(nb_levers : usize, variance : f64, confidence : f64) -> Result<Self,IdentificationError> {
+    Ok(TrackAndStop {
+      variance,
+      confidence,
+      estimator : sample_average(nb_levers)?,
+    })
+  }
+
+  // Optimal proportions of pulls for normal rewards. With x_a the
+  // ratio of the pulls of lever a to the pulls of the best lever,
+  // they solve sum of x_a^2 = 1, where x_a/(1 + x_a) is proportional
+  // to 1/gap_a^2.
+  fn proportions(&self) -> Vec<f64> {
+    let means = self.estimator.all(self.estimator.nb_levers());
+    let leader = best(&self.estimator);
+    let gaps : Vec<f64> = means.iter().map(|mean| means[leader] - mean).collect();
+    if gaps.iter().enumerate().any(|(lever,gap)| lever != leader && *gap <= 0.0) {
+      return vec![1.0 / (means.len() as f64); means.len()];
+    }
+    let ratios = |y : f64| -> Vec<f64> {
+      gaps.iter()
+          .enumerate()
+          .map(|(lever,gap)| if lever == leader {
+                               1.0
+                             } else {
+                               let r = y / (gap*gap);
+                               r / (1.0 - r)
+                             })
+          .collect()
+    };
+    let min_gap = gaps.iter()
+                      .enumerate()
+                      .filter(|(lever,_)| *lever != leader)
+                      .map(|(_,gap)| gap*gap)
+                      .fold(f64::INFINITY,f64::min);
+    let (mut low, mut high) = (0.0, min_gap);
+    for _ in 0..100 {
+      let mid = (low + high) / 2.0;
+      let total : f64 = ratios(mid).iter()
+                                   .enumerate()
+                                   .filter(|(lever,_)| *lever != leader)
+                                   .map(|(_,x)| x*x)
+                                   .sum();
+      if total < 1.0 { low = mid; } else { high = mid; }
+    }
+    let weights = ratios((low + high) / 2.0);
+    let total : f64 = weights.iter().sum();
+    weights.iter().map(|w| w / total).collect()
+  }
+}
+
+impl Identifier for TrackAndStop {
+
+  fn next<V: Rng>(&mut self, _rng : &mut V) -> Option<usize> {
+    if let Some(lever) = untried(&self.estimator) {
+      return Some(lever);
+    }
+    let nb_levers = self.estimator.nb_levers();
+    let counts : Vec<f64> = (0..nb_levers).map(|lever| self.estimator.count(lever)).collect();
+    let time : f64 = counts.iter().sum();
+    if gaussian_glr(&self.estimator,self.variance) > glr_threshold(time,self.confidence) {
+      return None;
+    }
+    // Forced exploration of the levers pulled less than sqrt(t).
+    let least = helper::indices_max(&counts.iter().map(|x| -x).collect::<Vec<f64>>())[0];
+    if counts[least] < time.sqrt() - (nb_levers as f64)/2.0 {
+      return Some(least);
+    }
+    let lags : Vec<f64> = self.proportions()
+                              .iter()
+                              .zip(counts.iter())
+                              .map(|(w,count)| time*w - count)
+                              .collect();
+    Some(helper::indices_max(&lags)[0])
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.estimator.update(lever,reward);
+  }
+
+  fn recommend(&self) -> usize {
+    best(&self.estimator)
+  }
+}
+
+#[derive(Clone)]
+pub(crate) struct TopTwoThompson {
+  variance : f64,
+  leader_proba : f64,
+  confidence : f64,
+  estimator : SampleAverage,
+}
+
+impl TopTwoThompson {
+
+  // Resamplings to find a challenger, before falling back on the
+  // second lever of the last sample.
+  const NB_RESAMPLES : usize = 100;
+
+  pub(crate) fn new(nb_levers : usize,
+                    variance : f64,
+                    leader_proba : f64,
+                    confidence : f64) -> Result<Self,IdentificationError> {
+    Ok(TopTwoThompson {
+      variance,
+      leader_proba,
+      confidence,
+      estimator : sample_average(nb_levers)?,
+    })
+  }
+
+  // Levers sorted by a sample of the posterior under a flat prior.
+  fn sample<V : Rng>(&self, rng : &mut V) -> Vec<usize> {
+    let samples : Vec<f64> =
+      (0..self.estimator.nb_levers())
+        .map(|lever| {
+          let std = (self.variance / self.estimator.count(lever)).sqrt();
+          Normal::new(self.estimator.estimate(lever),std).unwrap().sample(rng)
+        })
+        .collect();
+    let mut levers : Vec<usize> = (0..samples.len()).collect();
+    levers.sort_by(|a,b| samples[*b].total_cmp(&samples[*a]));
+    levers
+  }
+}
+
+impl Identifier for TopTwoThompson {
+
+  fn next<V: Rng>(&mut self, rng : &mut V) -> Option<usize> {
+    if let Some(lever) = untried(&self.estimator) {
+      return Some(lever);
+    }
+    let time : f64 = (0..self.estimator.nb_levers()).map(|lever| self.estimator.count(lever))
+                                                     .sum();
+    if gaussian_glr(&self.estimator,self.variance) > glr_threshold(time,self.confidence) {
+      return None;
+    }
+    let levers = self.sample(rng);
+    let leader = levers[0];
+    if rng.gen_bool(self.leader_proba) {
+      return Some(leader);
+    }
+    let mut challenger = levers[1];
+    for _ in 0..Self::NB_RESAMPLES {
+      let levers = self.sample(rng);
+      if levers[0] != leader {
+        challenger = levers[0];
+        break;
+      }
+    }
+    Some(challenger)
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.estimator.update(lever,reward);
+  }
+
+  fn recommend(&self) -> usize {
+    best(&self.estimator)
+  }
+}
+
+#[derive(Clone)]
+pub(crate) struct SuccessiveHalving {
+  budget : usize,
+  nb_rounds : usize,
+  // Levers still competing, and pulls of each in this round.
+  remaining : Vec<usize>,
+  pulls_per_lever : usize,
+  // Pulls left in this round, in order.
+  queue : Vec<usize>,
+  estimator : SampleAverage,
+}
+
+impl SuccessiveHalving {
+
+  pub(crate) fn new(nb_levers : usize, budget : usize) -> Result<Self,IdentificationError> {
+    let nb_rounds = (nb_levers as f64).log2().ceil().max(1.0) as usize;
+    let estimator = sample_average(nb_levers)?;
+    if budget < nb_levers*nb_rounds {
+      return Err(IdentificationError::SmallBudget(nb_rounds));
+    }
+    let mut halving = SuccessiveHalving {
+      budget,
+      nb_rounds,
+      remaining : (0..nb_levers).collect(),
+      pulls_per_lever : 0,
+      queue : Vec::new(),
+      estimator,
+    };
+    halving.start_round();
+    Ok(halving)
+  }
+
+  // Each round gets the same share of the budget, split equally
+  // between the remaining levers, so the budget is never exceeded.
+  fn start_round(&mut self) {
+    self.pulls_per_lever = self.budget / (self.remaining.len()*self.nb_rounds);
+    self.queue = self.remaining.iter()
+                               .flat_map(|lever| vec![*lever;self.pulls_per_lever])
+                               .rev()
+                               .collect();
+  }
+}
+
+impl Identifier for SuccessiveHalving {
+
+  #[allow(clippy::manual_div_ceil)]
+  fn next<V: Rng>(&mut self, _rng : &mut V) -> Option<usize> {
+    if self.queue.is_empty() {
+      if self.remaining.len() <= 1 {
+        return None;
+      }
+      // Keep the best half, with all the rewards of each lever.
+      let estimator = &self.estimator;
+      self.remaining.sort_by(|a,b| estimator.estimate(*b).total_cmp(&estimator.estimate(*a)));
+      self.remaining.truncate((self.remaining.len() + 1) / 2);
+      if self.remaining.len() <= 1 {
+        return None;
+      }
+      self.start_round();
+    }
+    self.queue.pop()
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.estimator.update(lever,reward);
+  }
+
+  fn recommend(&self) -> usize {
+    *self.remaining.iter()
+                   .max_by(|a,b| self.estimator.estimate(**a)
+                                               .total_cmp(&self.estimator.estimate(**b)))
+                   .unwrap()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problems::BanditStationary;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_identification() {
+        let algorithms = [IdentificationInit::LUCBInit {nb_levers : 4, range : 4.0, confidence : 0.05},
+                          IdentificationInit::TrackAndStopInit {nb_levers : 4, variance : 1.0,
+                                                                confidence : 0.05},
+                          IdentificationInit::TopTwoThompsonInit {nb_levers : 4, variance : 1.0,
+                                                                  leader_proba : 0.5,
+                                                                  confidence : 0.05},
+                          IdentificationInit::SuccessiveHalvingInit {nb_levers : 4, budget : 2000}];
+        let mut rng = StdRng::seed_from_u64(0);
+        let problem = BanditInit::StationaryInit {nb_levers : 4, init_vals : (0.0,1.0)};
+        let results = run_reprod_identification(&algorithms,problem,&mut rng,20,100_000).unwrap();
+        for result in results.iter() {
+            assert_eq!(result.recommended.len(),20);
+            assert!(result.error_rate <= 0.2, "{:?}", result);
+            assert!(result.sample_complexity > 0.0);
+        }
+        assert!(results[3].samples.iter().all(|x| *x <= 2000));
+        // With a gap of 1 between the two best levers, every try stops
+        // on its own.
+        let bandit : BanditEnum = BanditStationary::with_means(vec![0.0,0.5,1.0,2.0],1.0).into();
+        for algorithm in algorithms.iter() {
+            let tries : Vec<(usize,usize,bool,bool)> =
+              (0..20).map(|_| identify(create_identifier(algorithm).unwrap(),bandit.clone(),100_000,&mut rng))
+                     .collect();
+            let result = summarize(&tries);
+            assert_eq!(result.truncated,0, "{:?}", result);
+            assert!(result.error_rate <= 0.1, "{:?}", result);
+        }
+    }
+
+    #[test]
+    fn test_halving_budget() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let problem = BanditInit::StationaryInit {nb_levers : 5, init_vals : (0.0,1.0)};
+        for budget in 15..40 {
+            let algorithm = IdentificationInit::SuccessiveHalvingInit {nb_levers : 5, budget};
+            // Stopping exactly at max_pulls does not truncate the try.
+            let results = run_reprod_identification(&[algorithm],problem.clone(),&mut rng,5,budget).unwrap();
+            assert!(results[0].samples.iter().all(|x| *x <= budget));
+            assert_eq!(results[0].truncated,0);
+        }
+        // Two levers pulled once each, and the try stops at max_pulls.
+        let algorithm = IdentificationInit::SuccessiveHalvingInit {nb_levers : 2, budget : 2};
+        let problem = BanditInit::StationaryInit {nb_levers : 2, init_vals : (0.0,1.0)};
+        let results = run_reprod_identification(&[algorithm],problem.clone(),&mut rng,1,2).unwrap();
+        assert_eq!((results[0].samples[0],results[0].truncated),(2,0));
+        // Budgets which cannot be split, and empty problems, are errors.
+        let algorithm = IdentificationInit::SuccessiveHalvingInit {nb_levers : 4, budget : 7};
+        assert_eq!(run_reprod_identification(&[algorithm],problem.clone(),&mut rng,1,10),
+                   Err(IdentificationError::SmallBudget(2)));
+        let algorithm = IdentificationInit::LUCBInit {nb_levers : 0, range : 1.0, confidence : 0.05};
+        assert_eq!(run_reprod_identification(&[algorithm],problem,&mut rng,1,10),
+                   Err(IdentificationError::NoLever));
+    }
+}
@@ -1,9 +1,17 @@
+#![allow(clippy::upper_case_acronyms)]
+
 extern crate rand;
 extern crate rand_distr;
 extern crate enum_dispatch;
 extern crate rayon;
 extern crate gnuplot;
+extern crate serde;
+extern crate serde_json;
+#[cfg(feature = "server")]
+extern crate tiny_http;
 
+use std::error::Error;
+use std::fmt;
 use std::iter::once;
 use std::ops::Range;
 
@@ -15,30 +23,41 @@ mod experiments;
 mod problems;
 mod policies;
 mod estimators;
+mod schedules;
 mod helper;
+mod detectors;
+mod evaluation;
+mod contextual;
+mod session;
+mod identification;
+mod snapshot;
+#[cfg(feature = "server")]
+mod server;
 
-pub use problems::BanditInit;
-pub use policies::PolicyInit;
-pub use estimators::EstimatorInit;
+pub use problems::{BanditInit,DriftModel,Changepoints,HeavyTail,load_replay_log};
+pub use policies::{PolicyInit,Divergence};
+pub use estimators::{EstimatorInit,InitialValues,StepSize};
+pub use schedules::Schedule;
+pub use experiments::{ClockInit,Experiment};
+pub use session::{Session,Decision,SessionError};
+pub use contextual::{ContextualBanditInit,ContextualPolicyInit};
+pub use contextual::{run_contextual_experiments,run_reprod_contextual_experiments};
+pub use evaluation::{off_policy_evaluation,off_policy_evaluation_reprod};
+pub use evaluation::{Estimate,OffPolicyEstimates,EvaluationError};
+pub use identification::{IdentificationInit,IdentificationResults,IdentificationError};
+pub use identification::{run_identification,run_reprod_identification};
+pub use snapshot::{SessionSnapshot,ExperimentSnapshot,SnapshotError,SNAPSHOT_VERSION};
+#[cfg(feature = "server")]
+pub use server::{Server,PolicyConfig};
 use problems::create_bandit;
 use policies::create_policy;
-use experiments::Experiment;
-use experiments::Step;
+use experiments::{Step,Runnable};
 
 pub fn run_experiments(policies : &[PolicyInit],
                        problem : BanditInit,
                        nb_tries : usize,
                        len_exp : usize) -> Vec<Vec<Vec<Step>>> {
-
-  make_vec_experiment(policies,problem,&mut rand::thread_rng(),nb_tries)
-    .into_par_iter()
-    .map(|exps|
-      exps.into_par_iter()
-          .map(|exp| exp.steps(len_exp, &mut rand::thread_rng())
-          )
-          .collect::<Vec<Vec<Step>>>()
-    )
-    .collect()
+  run_clocked_experiments(policies,problem,ClockInit::default(),false,nb_tries,len_exp)
 }
 
 pub fn run_reprod_experiments<T> (policies : &[PolicyInit],
@@ -47,53 +66,190 @@ pub fn run_reprod_experiments<T> (policies : &[PolicyInit],
                                   nb_tries : usize,
                                   len_exp : usize) -> Vec<Vec<Vec<Step>>>
   where T : Rng {
+  run_reprod_clocked_experiments(policies,problem,ClockInit::default(),false,rng,nb_tries,len_exp)
+}
 
-  make_vec_experiment(policies,problem,rng,nb_tries)
-    .into_iter()
-    .map(|exps|
-      exps.into_iter()
-          .map(|exp| exp.steps(len_exp, rng))
-          .collect::<Vec<Vec<Step>>>()
-    )
-    .collect()
+// Like run_experiments, with a custom timing between the policy
+// and the environment. With record_probabilities, the steps also
+// hold the probabilities of policies which can only estimate them
+// (see Experiment::record_probabilities).
+pub fn run_clocked_experiments(policies : &[PolicyInit],
+                               problem : BanditInit,
+                               clock : ClockInit,
+                               record_probabilities : bool,
+                               nb_tries : usize,
+                               len_exp : usize) -> Vec<Vec<Vec<Step>>> {
+
+  let exps = make_vec_experiment(policies,&mut rand::thread_rng(),nb_tries,|policy,rng|
+    Experiment::from_init(policy,&problem,clock,len_exp,rng).record_probabilities(record_probabilities)
+  );
+  run_vec_experiment(exps,len_exp)
 }
 
-fn make_vec_experiment<T>(policies : &[PolicyInit],
-                          problem : BanditInit,
-                          rng : &mut T,
-                          nb_tries : usize) -> Vec<Vec<Experiment>>
+pub fn run_reprod_clocked_experiments<T> (policies : &[PolicyInit],
+                                          problem : BanditInit,
+                                          clock : ClockInit,
+                                          record_probabilities : bool,
+                                          rng : &mut T,
+                                          nb_tries : usize,
+                                          len_exp : usize) -> Vec<Vec<Vec<Step>>>
   where T : Rng {
 
-  policies.iter()
-          .map(|x| once(x).cycle()
-                          .take(nb_tries)
-                          .map(|policy|
-                            Experiment::new(create_policy(policy),
-                                            create_bandit(&problem,rng))
-                          )
-                          .collect::<Vec<Experiment>>()
-          )
-          .collect()
+  let exps = make_vec_experiment(policies,rng,nb_tries,|policy,rng|
+    Experiment::from_init(policy,&problem,clock,len_exp,rng).record_probabilities(record_probabilities)
+  );
+  run_reprod_vec_experiment(exps,rng,len_exp)
 }
 
+// nb_tries experiments for each initialisation, built by make.
+// Shared by the runners of context-free and contextual experiments.
+pub(crate) fn make_vec_experiment<I,E,T,F>(inits : &[I],
+                                           rng : &mut T,
+                                           nb_tries : usize,
+                                           mut make : F) -> Vec<Vec<E>>
+  where T : Rng, F : FnMut(&I,&mut T) -> E {
+
+  inits.iter()
+       .map(|x| once(x).cycle()
+                       .take(nb_tries)
+                       .map(|init| make(init,rng))
+                       .collect::<Vec<E>>()
+       )
+       .collect()
+}
+
+pub(crate) fn run_vec_experiment<E>(exps : Vec<Vec<E>>,
+                                    len_exp : usize) -> Vec<Vec<Vec<Step>>>
+  where E : Runnable {
+
+  exps.into_par_iter()
+      .map(|exps|
+        exps.into_par_iter()
+            .map(|exp| exp.steps(len_exp, &mut rand::thread_rng())
+            )
+            .collect::<Vec<Vec<Step>>>()
+      )
+      .collect()
+}
+
+pub(crate) fn run_reprod_vec_experiment<E,T>(exps : Vec<Vec<E>>,
+                                             rng : &mut T,
+                                             len_exp : usize) -> Vec<Vec<Vec<Step>>>
+  where E : Runnable, T : Rng {
+
+  exps.into_iter()
+      .map(|exps|
+        exps.into_iter()
+            .map(|exp| exp.steps(len_exp, rng))
+            .collect::<Vec<Vec<Step>>>()
+      )
+      .collect()
+}
+
+// Percentage of optimal actions at each time step, over nb_tries
+// experiments. Experiments which stopped early (replay of a log) are
+// left out of the steps they did not make.
 pub fn optimal_percentage(results : Vec<Vec<Vec<Step>>>,
                           nb_tries : usize,
                           len_exp : usize) -> Vec<Vec<f64>> {
   results.iter()
          .map(|exps| exps.iter()
-                         .fold(vec![0.0;len_exp],|acc,results|
-                            acc.iter()
-                               .zip(results.iter())
-                               .map(|(acc_val,step)| *acc_val+((step.optimal as usize) as f64))
-                               .collect()
-                         )
+                         .fold(vec![(0.0,nb_tries as f64);len_exp],|mut acc,results| {
+                            for (nb,acc_val) in acc.iter_mut().enumerate() {
+                              match results.get(nb) {
+                                Some(step) => acc_val.0 += (step.optimal as usize) as f64,
+                                None => acc_val.1 -= 1.0,
+                              }
+                            }
+                            acc
+                         })
+                         .into_iter()
+                         .map(|(x,nb) : (f64,f64)| x/nb.max(1.0))
+                         .collect()
+         )
+         .collect()
+}
+
+// Average reward at each time step. Like for optimal_percentage,
+// experiments which stopped early only count for the steps they made.
+pub fn average_reward(results : &[Vec<Vec<Step>>],
+                      len_exp : usize) -> Vec<Vec<f64>> {
+  results.iter()
+         .map(|exps| exps.iter()
+                         .fold(vec![(0.0,0.0);len_exp],|mut acc,results| {
+                            for (acc_val,step) in acc.iter_mut().zip(results.iter()) {
+                              acc_val.0 += step.reward;
+                              acc_val.1 += 1.0;
+                            }
+                            acc
+                         })
                          .into_iter()
-                         .map(|x| x/(nb_tries as f64))
+                         .map(|(x,nb) : (f64,f64)| x/nb.max(1.0))
                          .collect()
          )
          .collect()
 }
 
+// Indices of the steps of an experiment right after a changepoint.
+pub fn changepoints(steps : &[Step]) -> Vec<usize> {
+  steps.iter()
+       .enumerate()
+       .filter(|(_,step)| step.changepoint)
+       .map(|(nb,_)| nb)
+       .collect()
+}
+
+// Quality of the change detection of a policy, averaged over tries.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct DetectionStats {
+  // Alarms per experiment which do not detect a changepoint.
+  pub false_alarms : f64,
+  // Proportion of changepoints never detected.
+  pub missed : f64,
+  // Average number of steps between a changepoint and its detection.
+  pub mean_delay : f64,
+}
+
+// A changepoint is detected by the first alarm after it, if this alarm
+// comes before the next changepoint. Every other alarm is false.
+pub fn detection_statistics(results : &[Vec<Vec<Step>>]) -> Vec<DetectionStats> {
+  results.iter()
+         .map(|exps| {
+           let (mut false_alarms, mut missed, mut changes) = (0.0, 0.0, 0.0);
+           let (mut delays, mut detected) = (0.0, 0.0);
+           for steps in exps.iter() {
+             let mut last_change : Option<usize> = None;
+             for (nb,step) in steps.iter().enumerate() {
+               if step.changepoint {
+                 if last_change.is_some() {
+                   missed += 1.0;
+                 }
+                 changes += 1.0;
+                 last_change = Some(nb);
+               }
+               if step.alarm {
+                 match last_change.take() {
+                   Some(change) => {
+                     delays += (nb - change) as f64;
+                     detected += 1.0;
+                   },
+                   None => false_alarms += 1.0,
+                 }
+               }
+             }
+             if last_change.is_some() {
+               missed += 1.0;
+             }
+           }
+           DetectionStats {
+             false_alarms : false_alarms / (exps.len().max(1) as f64),
+             missed : if changes > 0.0 { missed / changes } else { 0.0 },
+             mean_delay : if detected > 0.0 { delays / detected } else { f64::NAN },
+           }
+         })
+         .collect()
+}
+
 pub fn plot_results(results : &[Vec<f64>],
                     names : &[&str],
                     len_exp : usize) {
@@ -117,55 +273,83 @@ pub fn plot_results(results : &[Vec<f64>],
   output.show().unwrap();
 }
 
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum StudyError {
+  // The policy has no parameter the study knows how to scale.
+  UnsupportedPolicy,
+}
+
+impl fmt::Display for StudyError {
 
+  fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      StudyError::UnsupportedPolicy =>
+        write!(f, "parameter studies are only available for EGreedy, Softmax and UCB"),
+    }
+  }
+}
+
+impl Error for StudyError {}
+
+// Plot the average reward of the policy against its parameter, the
+// first value being multiplied by each power of 2 in range.
 pub fn run_parameter_study(policy : &PolicyInit,
                            problem : &BanditInit,
                            len_exp : usize,
-                           range : Range<u32>) {
+                           range : Range<u32>) -> Result<(),StudyError> {
 
+  // The whole schedule is scaled, and plotted against its first value.
   let policies : Vec<PolicyInit>;
   let step_base : f64;
-  let egreedy : bool;
-  let ucb : bool;
+  let caption : &str;
   match *policy {
-    PolicyInit::EGreedyInit {nb_levers, expl_proba, est} => {
+    PolicyInit::EGreedyInit {nb_levers, ref expl_proba, est} => {
       policies =
         range.clone()
              .map(|x| PolicyInit::EGreedyInit {nb_levers,
-                                               expl_proba : expl_proba*(2.0_f64).powi(x as i32),
+                                               expl_proba : expl_proba.scale((2.0_f64).powi(x as i32)),
+                                               est})
+             .collect();
+      step_base = expl_proba.value(0);
+      caption = "EGreedy";},
+    PolicyInit::SoftmaxInit {nb_levers, ref temperature, est} => {
+      policies =
+        range.clone()
+             .map(|x| PolicyInit::SoftmaxInit {nb_levers,
+                                               temperature : temperature.scale((2.0_f64).powi(x as i32)),
                                                est})
              .collect();
-      step_base = expl_proba;
-      egreedy = true;
-      ucb = false;},
-    PolicyInit::UCBInit {nb_levers, step, est} => {
+      step_base = temperature.value(0);
+      caption = "Softmax";},
+    PolicyInit::UCBInit {nb_levers, ref step, est} => {
       policies =
         range.clone()
              .map(|x| PolicyInit::UCBInit {nb_levers,
-                                           step : step*(2.0_f64).powi(x as i32),
+                                           step : step.scale((2.0_f64).powi(x as i32)),
                                            est})
              .collect();
-      step_base = step;
-      egreedy = true;
-      ucb = false;},
-
+      step_base = step.value(0);
+      caption = "UCB";},
+    _ => return Err(StudyError::UnsupportedPolicy),
   }
   let results : Vec<f64> =
     policies.iter()
             .map(|policy| {
-              let exp = Experiment::new(create_policy(policy),
-                                        create_bandit(&problem,&mut rand::thread_rng()));
+              let exp = Experiment::new(create_policy(policy,Some(len_exp)),
+                                        create_bandit(problem,&mut rand::thread_rng()),
+                                        ClockInit::default());
               let result = exp.steps(len_exp, &mut rand::thread_rng());
+              let nb_steps = result.len().max(1) as f64;
               result.into_iter()
                     .map(|x| x.reward)
-                    .sum::<f64>() / (len_exp as f64)
+                    .sum::<f64>() / nb_steps
             })
             .collect();
 
   let mut output = Figure::new();
   let axes =
     output.axes2d()
-          .set_title("Average reward over first 1000 steps", &[])
+          .set_title(&format!("Average reward over first {} steps", len_exp), &[])
           .set_legend(Graph(0.5), Graph(0.9), &[], &[])
           .set_x_log(Some(2.0))
           .set_x_label("Value of parameter", &[])
@@ -173,16 +357,10 @@ pub fn run_parameter_study(policy : &PolicyInit,
 
   let time_steps : &[f64] = &range.map(|x| step_base*(2.0_f64).powi(x as i32))
                                   .collect::<Vec<f64>>()[..];
-  match (egreedy,ucb) {
-    (true,false) => {axes.lines(time_steps,
-                               results,
-                               &[Caption("EGreedy")],
-                              );},
-    (false,true) => {axes.lines(time_steps,
-                               results,
-                               &[Caption("UCB")],
-                              );},
-    _ => {panic!("Impossible!");},
-  }
+  axes.lines(time_steps,
+             results,
+             &[Caption(caption)],
+  );
   output.show().unwrap();
+  Ok(())
 }
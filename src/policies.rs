@@ -1,34 +1,179 @@
 use crate::estimators::{Estimator,EstimatorInit,EstimatorEnum, create_estimator};
+use crate::detectors::{Cusum,GLR,WindowTest};
 use crate::helper;
+use crate::schedules::Schedule;
 
 use rand::Rng;
+use rand::distributions::{Distribution,WeightedIndex};
 use rand::prelude::IteratorRandom;
 use enum_dispatch::enum_dispatch;
+use serde::{Serialize,Deserialize};
+
+// Number of sampled decisions used to estimate the probabilities
+// of randomized policies.
+pub(crate) const NB_SAMPLES : usize = 100;
 
 #[derive(Clone)]
 pub enum PolicyInit<'a> {
   EGreedyInit {nb_levers : usize,
-               expl_proba : f64,
+               expl_proba : Schedule,
                est : &'a EstimatorInit},
   UCBInit {nb_levers : usize,
-           step : f64,
+           step : Schedule,
            est : &'a EstimatorInit},
+  // Boltzmann exploration: levers are chosen with probabilities
+  // proportional to exp(estimate/temperature).
+  SoftmaxInit {nb_levers : usize,
+               temperature : Schedule,
+               est : &'a EstimatorInit},
+  // UCB with a CUSUM change detector on each lever, and uniform
+  // exploration with probability expl_proba.
+  CusumUCBInit {nb_levers : usize,
+                step : f64,
+                expl_proba : f64,
+                warmup : usize,
+                drift : f64,
+                threshold : f64,
+                est : &'a EstimatorInit},
+  // kl-UCB with a Bernoulli GLR change detector on each lever, of
+  // confidence 1 - confidence. Rewards should be in [0,1].
+  GLRklUCBInit {nb_levers : usize,
+                expl_proba : f64,
+                confidence : f64,
+                est : &'a EstimatorInit},
+  // UCB which restarts all levers when the rewards of the last
+  // window pulls of a lever change by more than threshold.
+  MUCBInit {nb_levers : usize,
+            step : f64,
+            expl_proba : f64,
+            window : usize,
+            threshold : f64,
+            est : &'a EstimatorInit},
+  // Thompson sampling: each lever is scored by a sample from the
+  // posterior of the estimator, which should be bayesian.
+  ThompsonInit {nb_levers : usize,
+                est : &'a EstimatorInit},
+  // KL-UCB: the index of a lever is the largest mean q such that
+  // count*d(estimate,q) <= ln(t) + c*ln(ln(t)).
+  KLUCBInit {nb_levers : usize,
+             divergence : Divergence,
+             c : f64,
+             est : &'a EstimatorInit},
+  // Bayes-UCB: the index of a lever is the quantile of order
+  // 1 - 1/t of the posterior of the estimator, which should be
+  // bayesian.
+  BayesUCBInit {nb_levers : usize,
+                est : &'a EstimatorInit},
+  // UCB-V, with the empirical Bernstein bound of rewards in an
+  // interval of length range, and an exploration of zeta*ln(t).
+  UCBVInit {nb_levers : usize,
+            range : f64,
+            zeta : f64,
+            est : &'a EstimatorInit},
+  // UCB1-Tuned, for rewards in [0,1].
+  UCBTunedInit {nb_levers : usize,
+                est : &'a EstimatorInit},
+  // MOSS, for rewards in [0,1]. Without a horizon, the current
+  // time is used instead (anytime MOSS).
+  MOSSInit {nb_levers : usize,
+            est : &'a EstimatorInit},
+  // Pull each lever nb_explore times in turn, and then always the
+  // lever with the best estimate.
+  ExploreThenCommitInit {nb_levers : usize,
+                         nb_explore : usize,
+                         est : &'a EstimatorInit},
+  // Pull the remaining levers in turn, and remove a lever once its
+  // Hoeffding upper bound is below the lower bound of another. The
+  // rewards must lie in an interval of length range, and the best
+  // lever is kept with probability 1 - confidence.
+  SuccessiveEliminationInit {nb_levers : usize,
+                             range : f64,
+                             confidence : f64,
+                             est : &'a EstimatorInit},
+}
+
+// Divergence between reward distributions of a one-parameter
+// family, given by their means.
+#[derive(Clone,Copy,Debug,PartialEq,Serialize,Deserialize)]
+pub enum Divergence {
+  // Rewards in [0,1].
+  Bernoulli,
+  // Normal rewards of known variance.
+  Gaussian {variance : f64},
+  // Non-negative rewards.
+  Poisson,
+}
+
+impl Divergence {
+
+  // Largest mean q such that d(mean,q) <= bound.
+  pub(crate) fn upper_mean(&self, mean : f64, bound : f64) -> f64 {
+    match *self {
+      Divergence::Bernoulli =>
+        helper::kl_ucb(mean.clamp(0.0,1.0),bound,helper::kl_bernoulli,1.0),
+      Divergence::Gaussian {variance} =>
+        mean + (2.0*variance*bound).sqrt(),
+      // The divergence is above (q - mean)^2/(2q), which gives an
+      // upper end for the search.
+      Divergence::Poisson => {
+        let mean = mean.max(0.0);
+        let upper = mean + bound + (bound*bound + 2.0*mean*bound).sqrt();
+        helper::kl_ucb(mean,bound,helper::kl_poisson,upper)
+      },
+    }
+  }
 }
 
-pub(crate) fn create_policy(init_data : &PolicyInit) -> PolicyEnum {
+// The horizon is the number of pulls the policy will make, when
+// it is known in advance.
+pub(crate) fn create_policy(init_data : &PolicyInit, horizon : Option<usize>) -> PolicyEnum {
   match *init_data {
-    PolicyInit::EGreedyInit {nb_levers,expl_proba,est} =>
-      EGreedy::new(nb_levers,expl_proba,est).into(),
-    PolicyInit::UCBInit {nb_levers,step, est} =>
-      UCB::new(nb_levers,step,est).into(),
+    PolicyInit::EGreedyInit {nb_levers,ref expl_proba,est} =>
+      EGreedy::new(nb_levers,expl_proba.clone(),est).into(),
+    PolicyInit::SoftmaxInit {nb_levers,ref temperature,est} =>
+      Softmax::new(nb_levers,temperature.clone(),est).into(),
+    PolicyInit::UCBInit {nb_levers,ref step, est} =>
+      UCB::new(nb_levers,step.clone(),est).into(),
+    PolicyInit::CusumUCBInit {nb_levers,step,expl_proba,warmup,drift,threshold,est} =>
+      CusumUCB::new(nb_levers,step,expl_proba,Cusum::new(warmup,drift,threshold),est).into(),
+    PolicyInit::GLRklUCBInit {nb_levers,expl_proba,confidence,est} =>
+      GLRklUCB::new(nb_levers,expl_proba,GLR::new(confidence),est).into(),
+    PolicyInit::MUCBInit {nb_levers,step,expl_proba,window,threshold,est} =>
+      MUCB::new(nb_levers,step,expl_proba,WindowTest::new(window,threshold),est).into(),
+    PolicyInit::ThompsonInit {nb_levers,est} =>
+      Thompson::new(nb_levers,est).into(),
+    PolicyInit::KLUCBInit {nb_levers,divergence,c,est} =>
+      KLUCB::new(nb_levers,divergence,c,est).into(),
+    PolicyInit::BayesUCBInit {nb_levers,est} =>
+      BayesUCB::new(nb_levers,est).into(),
+    PolicyInit::UCBVInit {nb_levers,range,zeta,est} =>
+      IndexPolicy::new(nb_levers,Bonus::Bernstein {range,zeta},est).into(),
+    PolicyInit::UCBTunedInit {nb_levers,est} =>
+      IndexPolicy::new(nb_levers,Bonus::Tuned,est).into(),
+    PolicyInit::MOSSInit {nb_levers,est} =>
+      IndexPolicy::new(nb_levers,Bonus::MOSS {horizon},est).into(),
+    PolicyInit::ExploreThenCommitInit {nb_levers,nb_explore,est} =>
+      ExploreThenCommit::new(nb_levers,nb_explore,est).into(),
+    PolicyInit::SuccessiveEliminationInit {nb_levers,range,confidence,est} =>
+      SuccessiveElimination::new(nb_levers,range,confidence,est).into(),
   }
 }
 
 #[enum_dispatch]
-#[derive(Clone)]
+#[derive(Clone,Serialize,Deserialize)]
 pub(crate) enum PolicyEnum {
   EGreedy,
+  Softmax,
   UCB,
+  CusumUCB,
+  GLRklUCB,
+  MUCB,
+  Thompson,
+  KLUCB,
+  BayesUCB,
+  IndexPolicy,
+  ExploreThenCommit,
+  SuccessiveElimination,
 }
 
 #[enum_dispatch(PolicyEnum)]
@@ -40,21 +185,77 @@ pub(crate) trait Policy : Clone + Send {
   // Update its values based on the result of the
   // step.
   fn update(&mut self, lever : usize, reward : f64);
+
+  // Give the estimator used by the policy.
+  fn estimator(&self) -> &EstimatorEnum;
+
+  fn estimator_mut(&mut self) -> &mut EstimatorEnum;
+
+  // The environment moved by one tick.
+  fn tick(&mut self) {
+    self.estimator_mut().tick();
+  }
+
+  // Give the current estimates of all levers.
+  fn estimates(&self) -> Vec<f64> {
+    self.estimator().all(self.estimator().nb_levers())
+  }
+
+  // Give the probability of choosing each lever at the next
+  // decision. Exact when it can be computed, otherwise estimated
+  // by sampling decisions.
+  fn probabilities<V: Rng>(&self, rng: &mut V) -> Vec<f64>;
+
+  // Check if probabilities are computed exactly, rather than
+  // estimated from sampled decisions.
+  fn exact_probabilities(&self) -> bool {
+    true
+  }
+
+  // Check if the last update detected a change in the rewards.
+  fn alarm(&self) -> bool {
+    false
+  }
 }
 
-#[derive(Clone)]
+// Upper confidence bounds of the levers, with an infinite bound
+// for levers never tried.
+fn ucb_indices(estimates : &[f64], counts : &[f64], time : f64, step : f64) -> Vec<f64> {
+  estimates.iter()
+           .zip(counts.iter())
+           .map(|(est,count)| if *count > 0.0 {
+                                est + step*(time.ln() / count).sqrt()
+                              } else {
+                                f64::INFINITY
+                              })
+           .collect()
+}
+
+// Lever to explore at this time, for policies exploring each lever
+// in turn during a fraction expl_proba of the time.
+fn forced_exploration(nb_levers : usize, expl_proba : f64, time : usize) -> Option<usize> {
+  if expl_proba <= 0.0 {
+    return None;
+  }
+  let period = ((nb_levers as f64 / expl_proba).floor() as usize).max(nb_levers);
+  Some(time % period).filter(|lever| *lever < nb_levers)
+}
+
+#[derive(Clone,Serialize,Deserialize)]
 pub(crate) struct EGreedy {
   nb_levers : usize,
-  expl_proba : f64,
+  expl_proba : Schedule,
+  time : usize,
   estimator : EstimatorEnum,
 }
 
 impl EGreedy {
 
-  pub(crate) fn new(nb_levers : usize, expl_proba : f64, est : &EstimatorInit) -> Self {
+  pub(crate) fn new(nb_levers : usize, expl_proba : Schedule, est : &EstimatorInit) -> Self {
     EGreedy {
       nb_levers,
       expl_proba,
+      time : 0,
       estimator : create_estimator(est)
     }
   }
@@ -67,7 +268,7 @@ impl EGreedy {
 impl Policy for EGreedy {
 
   fn decide<V: Rng>(&self, rng: &mut V) -> usize {
-    if rng.gen_bool(self.expl_proba) {
+    if rng.gen_bool(self.expl_proba.value(self.time)) {
       self.explore(rng)
     } else {
       *self.estimator.optimal(self.nb_levers)
@@ -80,50 +281,951 @@ impl Policy for EGreedy {
   // Update its values based on the result of the
   // step.
   fn update(&mut self, lever : usize, reward : f64) {
+    self.time += 1;
     self.estimator.update(lever,reward);
   }
+
+  fn estimator(&self) -> &EstimatorEnum {
+    &self.estimator
+  }
+
+  fn estimator_mut(&mut self) -> &mut EstimatorEnum {
+    &mut self.estimator
+  }
+
+  fn probabilities<V: Rng>(&self, _rng: &mut V) -> Vec<f64> {
+    let expl_proba = self.expl_proba.value(self.time);
+    let mut probas = vec![expl_proba / (self.nb_levers as f64); self.nb_levers];
+    let optimals = self.estimator.optimal(self.nb_levers);
+    for lever in optimals.iter() {
+      probas[*lever] += (1.0 - expl_proba) / (optimals.len() as f64);
+    }
+    probas
+  }
 }
 
-#[derive(Clone)]
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct Softmax {
+  nb_levers : usize,
+  temperature : Schedule,
+  time : usize,
+  estimator : EstimatorEnum,
+}
+
+impl Softmax {
+
+  pub(crate) fn new(nb_levers : usize, temperature : Schedule, est : &EstimatorInit) -> Self {
+    Softmax {
+      nb_levers,
+      temperature,
+      time : 0,
+      estimator : create_estimator(est)
+    }
+  }
+}
+
+impl Policy for Softmax {
+
+  fn decide<V: Rng>(&self, rng: &mut V) -> usize {
+    WeightedIndex::new(self.probabilities(rng)).unwrap().sample(rng)
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.time += 1;
+    self.estimator.update(lever,reward);
+  }
+
+  fn estimator(&self) -> &EstimatorEnum {
+    &self.estimator
+  }
+
+  fn estimator_mut(&mut self) -> &mut EstimatorEnum {
+    &mut self.estimator
+  }
+
+  fn probabilities<V: Rng>(&self, _rng: &mut V) -> Vec<f64> {
+    helper::softmax(&self.estimator.all(self.nb_levers),self.temperature.value(self.time))
+  }
+}
+
+#[derive(Clone,Serialize,Deserialize)]
 pub(crate) struct UCB {
   nb_levers : usize,
-  step : f64,
-  time : f64,
-  counts : Vec<f64>,
+  step : Schedule,
+  time : usize,
   estimator : EstimatorEnum,
 }
 
 impl UCB {
 
-  pub(crate) fn new(nb_levers : usize, step : f64, est : &EstimatorInit) -> Self {
+  pub(crate) fn new(nb_levers : usize, step : Schedule, est : &EstimatorInit) -> Self {
     UCB {
       nb_levers,
       step,
-      time : 0.0,
-      counts : vec![0.0;nb_levers],
+      time : 0,
       estimator : create_estimator(est)
     }
   }
+
+  // Levers with the highest upper confidence bound. With an
+  // estimator forgetting old rewards, the time is the effective
+  // number of pulls it remembers.
+  fn optimal(&self) -> Vec<usize> {
+    let counts = self.estimator.effective_counts(self.nb_levers);
+    helper::indices_max(&ucb_indices(&self.estimator.all(self.nb_levers),
+                                     &counts,
+                                     counts.iter().sum(),
+                                     self.step.value(self.time)))
+  }
 }
 
 impl Policy for UCB {
 
   fn decide<V: Rng>(&self, rng: &mut V) -> usize {
-    let est_counts : Vec<f64> =
+    *self.optimal()
+         .iter()
+         .choose(rng)
+         .unwrap()
+  }
+
+  // Update its values based on the result of the
+  // step. The time only drives the schedule of the step, the
+  // pulls of each lever are counted by the estimator.
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.time += 1;
+    self.estimator.update(lever,reward);
+  }
+
+  fn estimator(&self) -> &EstimatorEnum {
+    &self.estimator
+  }
+
+  fn estimator_mut(&mut self) -> &mut EstimatorEnum {
+    &mut self.estimator
+  }
+
+  // Only ties between the highest bounds are broken at random.
+  fn probabilities<V: Rng>(&self, _rng: &mut V) -> Vec<f64> {
+    helper::uniform_over(&self.optimal(),self.nb_levers)
+  }
+}
+
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct CusumUCB {
+  nb_levers : usize,
+  step : f64,
+  expl_proba : f64,
+  detectors : Vec<Cusum>,
+  alarm : bool,
+  estimator : EstimatorEnum,
+}
+
+impl CusumUCB {
+
+  pub(crate) fn new(nb_levers : usize,
+                    step : f64,
+                    expl_proba : f64,
+                    detector : Cusum,
+                    est : &EstimatorInit) -> Self {
+    CusumUCB {
+      nb_levers,
+      step,
+      expl_proba,
+      detectors : vec![detector;nb_levers],
+      alarm : false,
+      estimator : create_estimator(est),
+    }
+  }
+
+  fn optimal(&self) -> Vec<usize> {
+    let counts = self.estimator.effective_counts(self.nb_levers);
+    helper::indices_max(&ucb_indices(&self.estimator.all(self.nb_levers),
+                                     &counts,
+                                     counts.iter().sum(),
+                                     self.step))
+  }
+}
+
+impl Policy for CusumUCB {
+
+  fn decide<V: Rng>(&self, rng: &mut V) -> usize {
+    if rng.gen_bool(self.expl_proba) {
+      rng.gen_range(0,self.nb_levers)
+    } else {
+      *self.optimal()
+           .iter()
+           .choose(rng)
+           .unwrap()
+    }
+  }
+
+  // Only the lever raising the alarm is restarted.
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.estimator.update(lever,reward);
+    self.alarm = self.detectors[lever].update(reward);
+    if self.alarm {
+      self.detectors[lever].reset();
+      self.estimator.reset(lever);
+    }
+  }
+
+  fn estimator(&self) -> &EstimatorEnum {
+    &self.estimator
+  }
+
+  fn estimator_mut(&mut self) -> &mut EstimatorEnum {
+    &mut self.estimator
+  }
+
+  fn probabilities<V: Rng>(&self, _rng: &mut V) -> Vec<f64> {
+    let optimals = helper::uniform_over(&self.optimal(),self.nb_levers);
+    optimals.iter()
+            .map(|p| self.expl_proba / (self.nb_levers as f64) + (1.0 - self.expl_proba)*p)
+            .collect()
+  }
+
+  fn alarm(&self) -> bool {
+    self.alarm
+  }
+}
+
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct GLRklUCB {
+  nb_levers : usize,
+  expl_proba : f64,
+  time : usize,
+  detectors : Vec<GLR>,
+  alarm : bool,
+  estimator : EstimatorEnum,
+}
+
+impl GLRklUCB {
+
+  pub(crate) fn new(nb_levers : usize,
+                    expl_proba : f64,
+                    detector : GLR,
+                    est : &EstimatorInit) -> Self {
+    GLRklUCB {
+      nb_levers,
+      expl_proba,
+      time : 0,
+      detectors : vec![detector;nb_levers],
+      alarm : false,
+      estimator : create_estimator(est),
+    }
+  }
+
+  fn optimal(&self) -> Vec<usize> {
+    if let Some(lever) = forced_exploration(self.nb_levers,self.expl_proba,self.time) {
+      return vec![lever];
+    }
+    let counts = self.estimator.effective_counts(self.nb_levers);
+    let time : f64 = counts.iter().sum();
+    let indices : Vec<f64> =
       self.estimator.all(self.nb_levers)
                     .iter()
-                    .zip(self.counts.iter())
-                    .map( |x| x.0 + self.step*(self.time.ln() / *x.1).sqrt())
+                    .zip(counts.iter())
+                    .map(|(est,count)| if *count > 0.0 {
+                                         Divergence::Bernoulli.upper_mean(*est,
+                                                                          time.ln().max(0.0) / count)
+                                       } else {
+                                         f64::INFINITY
+                                       })
                     .collect();
-    *helper::indices_max(&est_counts)
-            .iter()
-            .choose(rng)
-            .unwrap()
+    helper::indices_max(&indices)
   }
+}
 
-  // Update its values based on the result of the
-  // step.
+impl Policy for GLRklUCB {
+
+  fn decide<V: Rng>(&self, rng: &mut V) -> usize {
+    *self.optimal()
+         .iter()
+         .choose(rng)
+         .unwrap()
+  }
+
+  // Only the lever raising the alarm is restarted.
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.time += 1;
+    self.estimator.update(lever,reward);
+    self.alarm = self.detectors[lever].update(reward);
+    if self.alarm {
+      self.detectors[lever].reset();
+      self.estimator.reset(lever);
+    }
+  }
+
+  fn estimator(&self) -> &EstimatorEnum {
+    &self.estimator
+  }
+
+  fn estimator_mut(&mut self) -> &mut EstimatorEnum {
+    &mut self.estimator
+  }
+
+  fn probabilities<V: Rng>(&self, _rng: &mut V) -> Vec<f64> {
+    helper::uniform_over(&self.optimal(),self.nb_levers)
+  }
+
+  fn alarm(&self) -> bool {
+    self.alarm
+  }
+}
+
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct MUCB {
+  nb_levers : usize,
+  step : f64,
+  expl_proba : f64,
+  // Steps since the last restart.
+  time : usize,
+  detectors : Vec<WindowTest>,
+  alarm : bool,
+  estimator : EstimatorEnum,
+}
+
+impl MUCB {
+
+  pub(crate) fn new(nb_levers : usize,
+                    step : f64,
+                    expl_proba : f64,
+                    detector : WindowTest,
+                    est : &EstimatorInit) -> Self {
+    MUCB {
+      nb_levers,
+      step,
+      expl_proba,
+      time : 0,
+      detectors : vec![detector;nb_levers],
+      alarm : false,
+      estimator : create_estimator(est),
+    }
+  }
+
+  fn optimal(&self) -> Vec<usize> {
+    match forced_exploration(self.nb_levers,self.expl_proba,self.time) {
+      Some(lever) => vec![lever],
+      None => {
+        let counts = self.estimator.effective_counts(self.nb_levers);
+        helper::indices_max(&ucb_indices(&self.estimator.all(self.nb_levers),
+                                         &counts,
+                                         counts.iter().sum(),
+                                         self.step))
+      },
+    }
+  }
+}
+
+impl Policy for MUCB {
+
+  fn decide<V: Rng>(&self, rng: &mut V) -> usize {
+    *self.optimal()
+         .iter()
+         .choose(rng)
+         .unwrap()
+  }
+
+  // All levers are restarted at an alarm.
   fn update(&mut self, lever : usize, reward : f64) {
+    self.time += 1;
     self.estimator.update(lever,reward);
+    self.alarm = self.detectors[lever].update(reward);
+    if self.alarm {
+      self.time = 0;
+      for lever in 0..self.nb_levers {
+        self.detectors[lever].reset();
+        self.estimator.reset(lever);
+      }
+    }
+  }
+
+  fn estimator(&self) -> &EstimatorEnum {
+    &self.estimator
+  }
+
+  fn estimator_mut(&mut self) -> &mut EstimatorEnum {
+    &mut self.estimator
+  }
+
+  fn probabilities<V: Rng>(&self, _rng: &mut V) -> Vec<f64> {
+    helper::uniform_over(&self.optimal(),self.nb_levers)
   }
+
+  fn alarm(&self) -> bool {
+    self.alarm
+  }
+}
+
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct Thompson {
+  nb_levers : usize,
+  estimator : EstimatorEnum,
+}
+
+impl Thompson {
+
+  pub(crate) fn new(nb_levers : usize, est : &EstimatorInit) -> Self {
+    Thompson {
+      nb_levers,
+      estimator : create_estimator(est),
+    }
+  }
+}
+
+impl Policy for Thompson {
+
+  fn decide<V: Rng>(&self, rng: &mut V) -> usize {
+    let samples : Vec<f64> = (0..self.nb_levers).map(|lever| self.estimator.sample(lever,rng))
+                                                .collect();
+    *helper::indices_max(&samples)
+             .iter()
+             .choose(rng)
+             .unwrap()
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.estimator.update(lever,reward);
+  }
+
+  fn estimator(&self) -> &EstimatorEnum {
+    &self.estimator
+  }
+
+  fn estimator_mut(&mut self) -> &mut EstimatorEnum {
+    &mut self.estimator
+  }
+
+  // Estimated from NB_SAMPLES sampled decisions.
+  fn probabilities<V: Rng>(&self, rng: &mut V) -> Vec<f64> {
+    let mut probas = vec![0.0;self.nb_levers];
+    for _ in 0..NB_SAMPLES {
+      probas[self.decide(rng)] += 1.0/(NB_SAMPLES as f64);
+    }
+    probas
+  }
+
+  fn exact_probabilities(&self) -> bool {
+    false
+  }
+}
+
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct KLUCB {
+  nb_levers : usize,
+  divergence : Divergence,
+  c : f64,
+  time : usize,
+  estimator : EstimatorEnum,
+}
+
+impl KLUCB {
+
+  pub(crate) fn new(nb_levers : usize, divergence : Divergence, c : f64, est : &EstimatorInit) -> Self {
+    KLUCB {
+      nb_levers,
+      divergence,
+      c,
+      time : 0,
+      estimator : create_estimator(est),
+    }
+  }
+
+  fn optimal(&self) -> Vec<usize> {
+    let log = (self.time.max(1) as f64).ln();
+    let exploration = log + self.c*log.ln().max(0.0);
+    let indices : Vec<f64> =
+      (0..self.nb_levers).map(|lever| {
+                           let count = self.estimator.effective_count(lever);
+                           if count > 0.0 {
+                             self.divergence.upper_mean(self.estimator.estimate(lever),
+                                                        exploration / count)
+                           } else {
+                             f64::INFINITY
+                           }
+                         })
+                         .collect();
+    helper::indices_max(&indices)
+  }
+}
+
+impl Policy for KLUCB {
+
+  fn decide<V: Rng>(&self, rng: &mut V) -> usize {
+    *self.optimal()
+         .iter()
+         .choose(rng)
+         .unwrap()
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.time += 1;
+    self.estimator.update(lever,reward);
+  }
+
+  fn estimator(&self) -> &EstimatorEnum {
+    &self.estimator
+  }
+
+  fn estimator_mut(&mut self) -> &mut EstimatorEnum {
+    &mut self.estimator
+  }
+
+  fn probabilities<V: Rng>(&self, _rng: &mut V) -> Vec<f64> {
+    helper::uniform_over(&self.optimal(),self.nb_levers)
+  }
+}
+
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct BayesUCB {
+  nb_levers : usize,
+  time : usize,
+  estimator : EstimatorEnum,
+}
+
+impl BayesUCB {
+
+  pub(crate) fn new(nb_levers : usize, est : &EstimatorInit) -> Self {
+    BayesUCB {
+      nb_levers,
+      time : 0,
+      estimator : create_estimator(est),
+    }
+  }
+
+  fn optimal(&self) -> Vec<usize> {
+    let order = 1.0 - 1.0/((self.time + 1) as f64);
+    let indices : Vec<f64> = (0..self.nb_levers).map(|lever| self.estimator.quantile(lever,order))
+                                                .collect();
+    helper::indices_max(&indices)
+  }
+}
+
+impl Policy for BayesUCB {
+
+  fn decide<V: Rng>(&self, rng: &mut V) -> usize {
+    *self.optimal()
+         .iter()
+         .choose(rng)
+         .unwrap()
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.time += 1;
+    self.estimator.update(lever,reward);
+  }
+
+  fn estimator(&self) -> &EstimatorEnum {
+    &self.estimator
+  }
+
+  fn estimator_mut(&mut self) -> &mut EstimatorEnum {
+    &mut self.estimator
+  }
+
+  fn probabilities<V: Rng>(&self, _rng: &mut V) -> Vec<f64> {
+    helper::uniform_over(&self.optimal(),self.nb_levers)
+  }
+}
+
+// Exploration bonus of an index policy: the index of a lever is
+// its estimate plus this bonus, computed from its reward variance
+// and number of pulls.
+#[derive(Clone,Copy,Serialize,Deserialize)]
+pub(crate) enum Bonus {
+  // Empirical Bernstein bound of Audibert, Munos and Szepesvari
+  // (UCB-V).
+  Bernstein {range : f64,
+             zeta : f64},
+  // UCB1-Tuned: the variance of rewards in [0,1] is at most 1/4.
+  Tuned,
+  // MOSS, with the current time when the horizon is unknown.
+  MOSS {horizon : Option<usize>},
+}
+
+impl Bonus {
+
+  fn value(&self, variance : f64, count : f64, time : usize, nb_levers : usize) -> f64 {
+    let log = (time.max(1) as f64).ln();
+    match *self {
+      Bonus::Bernstein {range,zeta} =>
+        (2.0*variance*zeta*log/count).sqrt() + 3.0*range*zeta*log/count,
+      Bonus::Tuned =>
+        (log/count*(variance + (2.0*log/count).sqrt()).min(0.25)).sqrt(),
+      Bonus::MOSS {horizon} => {
+        let horizon = horizon.unwrap_or(time).max(1) as f64;
+        ((horizon/(nb_levers as f64*count)).ln().max(0.0)/count).sqrt()
+      },
+    }
+  }
+}
+
+// UCB-V, UCB1-Tuned and MOSS, which only differ by their bonus.
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct IndexPolicy {
+  nb_levers : usize,
+  bonus : Bonus,
+  time : usize,
+  estimator : EstimatorEnum,
+}
+
+impl IndexPolicy {
+
+  pub(crate) fn new(nb_levers : usize, bonus : Bonus, est : &EstimatorInit) -> Self {
+    IndexPolicy {
+      nb_levers,
+      bonus,
+      time : 0,
+      estimator : create_estimator(est),
+    }
+  }
+
+  fn optimal(&self) -> Vec<usize> {
+    let indices : Vec<f64> =
+      (0..self.nb_levers).map(|lever| {
+                           let count = self.estimator.effective_count(lever);
+                           if count > 0.0 {
+                             self.estimator.estimate(lever)
+                               + self.bonus.value(self.estimator.reward_variance(lever),
+                                                  count,
+                                                  self.time,
+                                                  self.nb_levers)
+                           } else {
+                             f64::INFINITY
+                           }
+                         })
+                         .collect();
+    helper::indices_max(&indices)
+  }
+}
+
+impl Policy for IndexPolicy {
+
+  fn decide<V: Rng>(&self, rng: &mut V) -> usize {
+    *self.optimal()
+         .iter()
+         .choose(rng)
+         .unwrap()
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.time += 1;
+    self.estimator.update(lever,reward);
+  }
+
+  fn estimator(&self) -> &EstimatorEnum {
+    &self.estimator
+  }
+
+  fn estimator_mut(&mut self) -> &mut EstimatorEnum {
+    &mut self.estimator
+  }
+
+  fn probabilities<V: Rng>(&self, _rng: &mut V) -> Vec<f64> {
+    helper::uniform_over(&self.optimal(),self.nb_levers)
+  }
+}
+
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct ExploreThenCommit {
+  nb_levers : usize,
+  nb_explore : usize,
+  time : usize,
+  estimator : EstimatorEnum,
+}
+
+impl ExploreThenCommit {
+
+  pub(crate) fn new(nb_levers : usize, nb_explore : usize, est : &EstimatorInit) -> Self {
+    ExploreThenCommit {
+      nb_levers,
+      nb_explore,
+      time : 0,
+      estimator : create_estimator(est),
+    }
+  }
+
+  // While exploring, the levers pulled the least.
+  fn optimal(&self) -> Vec<usize> {
+    if self.time < self.nb_explore*self.nb_levers {
+      let counts : Vec<f64> = (0..self.nb_levers).map(|lever| -self.estimator.effective_count(lever))
+                                                 .collect();
+      helper::indices_max(&counts)
+    } else {
+      self.estimator.optimal(self.nb_levers)
+    }
+  }
+}
+
+impl Policy for ExploreThenCommit {
+
+  fn decide<V: Rng>(&self, rng: &mut V) -> usize {
+    *self.optimal()
+         .iter()
+         .choose(rng)
+         .unwrap()
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.time += 1;
+    self.estimator.update(lever,reward);
+  }
+
+  fn estimator(&self) -> &EstimatorEnum {
+    &self.estimator
+  }
+
+  fn estimator_mut(&mut self) -> &mut EstimatorEnum {
+    &mut self.estimator
+  }
+
+  fn probabilities<V: Rng>(&self, _rng: &mut V) -> Vec<f64> {
+    helper::uniform_over(&self.optimal(),self.nb_levers)
+  }
+}
+
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct SuccessiveElimination {
+  nb_levers : usize,
+  range : f64,
+  confidence : f64,
+  active : Vec<bool>,
+  estimator : EstimatorEnum,
+}
+
+impl SuccessiveElimination {
+
+  pub(crate) fn new(nb_levers : usize, range : f64, confidence : f64, est : &EstimatorInit) -> Self {
+    SuccessiveElimination {
+      nb_levers,
+      range,
+      confidence,
+      active : vec![true;nb_levers],
+      estimator : create_estimator(est),
+    }
+  }
+
+  // Hoeffding radius, with a union bound over levers and pulls.
+  fn radius(&self, count : f64) -> f64 {
+    let log = (4.0*(self.nb_levers as f64)*count*count / self.confidence).ln();
+    self.range*(log / (2.0*count)).sqrt()
+  }
+
+  // The remaining levers pulled the least.
+  fn optimal(&self) -> Vec<usize> {
+    let counts : Vec<f64> =
+      (0..self.nb_levers).map(|lever| if self.active[lever] {
+                                        -self.estimator.effective_count(lever)
+                                      } else {
+                                        f64::NEG_INFINITY
+                                      })
+                         .collect();
+    helper::indices_max(&counts)
+  }
+
+  fn eliminate(&mut self) {
+    let bounds : Vec<(f64,f64)> =
+      (0..self.nb_levers).map(|lever| {
+                           let count = self.estimator.effective_count(lever);
+                           if count > 0.0 {
+                             let radius = self.radius(count);
+                             let estimate = self.estimator.estimate(lever);
+                             (estimate - radius, estimate + radius)
+                           } else {
+                             (f64::NEG_INFINITY,f64::INFINITY)
+                           }
+                         })
+                         .collect();
+    let best_lower = (0..self.nb_levers).filter(|lever| self.active[*lever])
+                                        .map(|lever| bounds[lever].0)
+                                        .fold(f64::NEG_INFINITY,f64::max);
+    for (active,bound) in self.active.iter_mut().zip(bounds.iter()) {
+      *active = *active && bound.1 >= best_lower;
+    }
+  }
+}
+
+impl Policy for SuccessiveElimination {
+
+  fn decide<V: Rng>(&self, rng: &mut V) -> usize {
+    *self.optimal()
+         .iter()
+         .choose(rng)
+         .unwrap()
+  }
+
+  fn update(&mut self, lever : usize, reward : f64) {
+    self.estimator.update(lever,reward);
+    self.eliminate();
+  }
+
+  fn estimator(&self) -> &EstimatorEnum {
+    &self.estimator
+  }
+
+  fn estimator_mut(&mut self) -> &mut EstimatorEnum {
+    &mut self.estimator
+  }
+
+  fn probabilities<V: Rng>(&self, _rng: &mut V) -> Vec<f64> {
+    helper::uniform_over(&self.optimal(),self.nb_levers)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::estimators::InitialValues;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_egreedy_probabilities() {
+        let est = EstimatorInit::SampleAverageInit {nb_levers : 4};
+        let mut policy = EGreedy::new(4,0.2.into(),&est);
+        policy.update(2,1.0);
+        let probas = policy.probabilities(&mut StdRng::seed_from_u64(0));
+        assert!((probas[2] - 0.85).abs() < 1e-12);
+        assert!((probas[0] - 0.05).abs() < 1e-12);
+        assert!((probas.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_softmax_probabilities() {
+        let est = EstimatorInit::SampleAverageInit {nb_levers : 3};
+        let mut policy = Softmax::new(3,Schedule::Constant(1.0),&est);
+        policy.update(0,1000.0);
+        policy.update(1,999.0);
+        let mut rng = StdRng::seed_from_u64(0);
+        let probas = policy.probabilities(&mut rng);
+        let e = std::f64::consts::E;
+        assert!((probas[0] - e/(e + 1.0)).abs() < 1e-9);
+        assert!(probas[2] < 1e-100);
+        let greedy = Softmax::new(3,Schedule::Constant(0.0),&est);
+        assert_eq!(greedy.probabilities(&mut rng),vec![1.0/3.0;3]);        // Optimistic initial values must not make weights NaN.
+        let optimistic = EstimatorInit::PriorSampleAverageInit {nb_levers : 3,
+                                                                init : InitialValues::PerLever(vec![0.0,f64::INFINITY,0.0]),
+                                                                pseudo_counts : 1.0};
+        let policy = Softmax::new(3,Schedule::Constant(1.0),&optimistic);
+        assert_eq!(policy.decide(&mut rng),1);
+    }
+
+    #[test]
+    fn test_ucb_tries_every_lever() {
+        let est = EstimatorInit::SampleAverageInit {nb_levers : 3};
+        let mut policy = UCB::new(3,1.0.into(),&est);
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut tried = [false;3];
+        for _ in 0..3 {
+            let lever = policy.decide(&mut rng);
+            tried[lever] = true;
+            policy.update(lever,0.0);
+        }
+        assert!(tried.iter().all(|x| *x));
+        assert_eq!(policy.probabilities(&mut rng).iter().sum::<f64>(),1.0);
+    }
+
+    #[test]
+    fn test_ucb_counts_pulls() {
+        let est = EstimatorInit::SampleAverageInit {nb_levers : 2};
+        let mut policy = UCB::new(2,1.0.into(),&est);
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut best = 0;
+        for _ in 0..200 {
+            let lever = policy.decide(&mut rng);
+            best += (lever == 0) as usize;
+            policy.update(lever,if lever == 0 { 1.0 } else { 0.0 });
+        }
+        assert!(best > 150, "{}", best);
+    }
+
+    #[test]
+    fn test_thompson_finds_best_lever() {
+        let est = EstimatorInit::BetaBernoulliInit {nb_levers : 2, alpha : 1.0, beta : 1.0};
+        let mut policy = Thompson::new(2,&est);
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..200 {
+            let lever = policy.decide(&mut rng);
+            policy.update(lever,if lever == 1 { 1.0 } else { 0.0 });
+        }
+        assert!(policy.probabilities(&mut rng)[1] > 0.9);
+    }
+
+    #[test]
+    fn test_divergences() {
+        let gaussian = Divergence::Gaussian {variance : 1.0}.upper_mean(0.5,0.5);
+        assert!((gaussian - 1.5).abs() < 1e-12);
+        let bernoulli = Divergence::Bernoulli.upper_mean(0.5,0.1);
+        assert!(bernoulli > 0.5 && bernoulli < 1.0);
+        assert!((helper::kl_bernoulli(0.5,bernoulli) - 0.1).abs() < 1e-5);
+        let poisson = Divergence::Poisson.upper_mean(2.0,0.1);
+        assert!((helper::kl_poisson(2.0,poisson) - 0.1).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_index_policies_find_best_lever() {
+        let bernoulli = EstimatorInit::SampleAverageInit {nb_levers : 2};
+        let beta = EstimatorInit::BetaBernoulliInit {nb_levers : 2, alpha : 1.0, beta : 1.0};
+        let policies = [PolicyInit::KLUCBInit {nb_levers : 2, divergence : Divergence::Bernoulli,
+                                               c : 0.0, est : &bernoulli},
+                        PolicyInit::BayesUCBInit {nb_levers : 2, est : &beta},
+                        PolicyInit::UCBVInit {nb_levers : 2, range : 1.0, zeta : 1.2, est : &bernoulli},
+                        PolicyInit::UCBTunedInit {nb_levers : 2, est : &bernoulli},
+                        PolicyInit::MOSSInit {nb_levers : 2, est : &bernoulli}];
+        let mut rng = StdRng::seed_from_u64(0);
+        for init in policies.iter() {
+            let mut policy = create_policy(init,Some(500));
+            let mut pulls = [0;2];
+            for _ in 0..500 {
+                let lever = policy.decide(&mut rng);
+                pulls[lever] += 1;
+                let proba = if lever == 1 { 0.7 } else { 0.3 };
+                policy.update(lever,if rng.gen_bool(proba) { 1.0 } else { 0.0 });
+            }
+            assert!(pulls[1] > 400, "{:?}", pulls);
+        }
+    }
+
+    // Both explore uniformly first, so they need a longer run.
+    #[test]
+    fn test_elimination_policies_find_best_lever() {
+        let bernoulli = EstimatorInit::SampleAverageInit {nb_levers : 2};
+        let policies = [PolicyInit::ExploreThenCommitInit {nb_levers : 2, nb_explore : 30,
+                                                           est : &bernoulli},
+                        PolicyInit::SuccessiveEliminationInit {nb_levers : 2, range : 1.0,
+                                                               confidence : 0.05, est : &bernoulli}];
+        let mut rng = StdRng::seed_from_u64(0);
+        for init in policies.iter() {
+            let mut policy = create_policy(init,Some(1000));
+            let mut pulls = [0;2];
+            for _ in 0..1000 {
+                let lever = policy.decide(&mut rng);
+                pulls[lever] += 1;
+                let proba = if lever == 1 { 0.7 } else { 0.3 };
+                policy.update(lever,if rng.gen_bool(proba) { 1.0 } else { 0.0 });
+            }
+            assert!(pulls[1] > 700, "{:?}", pulls);
+        }
+    }
+
+    #[test]
+    fn test_change_detection() {
+        use crate::{BanditInit,Changepoints};
+        let est = EstimatorInit::SampleAverageInit {nb_levers : 2};
+        let policies = [PolicyInit::CusumUCBInit {nb_levers : 2, step : 1.0, expl_proba : 0.05,
+                                                  warmup : 20, drift : 0.1, threshold : 5.0,
+                                                  est : &est},
+                        PolicyInit::GLRklUCBInit {nb_levers : 2, expl_proba : 0.05,
+                                                  confidence : 0.01, est : &est},
+                        PolicyInit::MUCBInit {nb_levers : 2, step : 1.0, expl_proba : 0.05,
+                                              window : 40, threshold : 6.0, est : &est}];
+        let problem = BanditInit::PiecewiseStationaryInit {nb_levers : 2,
+                                                           init_vals : (0.5,0.3),
+                                                           changes : Changepoints::Fixed(vec![1000])};
+        let results = crate::run_reprod_experiments(&policies,problem,&mut StdRng::seed_from_u64(1),10,2000);
+        for stats in crate::detection_statistics(&results) {
+            assert!(stats.missed < 0.5, "{:?}", stats);
+            assert!(stats.false_alarms < 2.0, "{:?}", stats);
+        }
+    }
 }
@@ -1,11 +1,18 @@
 use crate::helper;
 
 use std::collections::HashSet;
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::{self,BufRead,BufReader};
 use std::iter::FromIterator;
+use std::path::Path;
+use std::sync::Arc;
 
 use rand::Rng;
-use rand_distr::{Normal, Distribution};
+use rand::seq::SliceRandom;
+use rand_distr::{Normal, Distribution, Exp1, Pareto, StudentT};
 use enum_dispatch::enum_dispatch;
+use serde::{Serialize,Deserialize};
 
 #[derive(Clone)]
 pub enum BanditInit {
@@ -16,6 +23,124 @@ pub enum BanditInit {
                       init_vals : (f64,f64),
                       walk : (f64,f64)
                     },
+  // Levers start with means sampled like for a stationary bandit,
+  // and then move according to the drift model at each step.
+  DriftingInit { nb_levers : usize,
+                 init_vals : (f64,f64),
+                 drift : DriftModel
+               },
+  // Levers have the same normal distributions as a stationary
+  // bandit, but their means are all sampled again at changepoints.
+  PiecewiseStationaryInit { nb_levers : usize,
+                            init_vals : (f64,f64),
+                            changes : Changepoints
+                          },
+  // Levers have means sampled like for a stationary bandit, and
+  // rewards add to them the noise scaled by init_vals.1.
+  HeavyTailedInit { nb_levers : usize,
+                    init_vals : (f64,f64),
+                    noise : HeavyTail
+                  },
+  // Logged (lever, reward) pairs, collected by choosing levers
+  // uniformly at random.
+  ReplayInit { nb_levers : usize,
+               log : Arc<Vec<(usize,f64)>>
+             },
+}
+
+#[derive(Clone,Serialize,Deserialize)]
+pub enum DriftModel {
+  // Gaussian random walk, with steps following walk.
+  RandomWalk { walk : (f64,f64) },
+  // Gaussian random walk, reflected to stay within bounds.
+  Reflected { walk : (f64,f64),
+              bounds : (f64,f64) },
+  // Gaussian random walk, with the steps of each lever following
+  // their own distribution.
+  PerLever { walks : Vec<(f64,f64)> },
+  // Means are pulled back toward mean at rate theta, with a
+  // gaussian noise of std sigma.
+  OrnsteinUhlenbeck { theta : f64,
+                      mean : f64,
+                      sigma : f64 },
+  // Means oscillate around their initial value, with a random
+  // phase for each lever.
+  Periodic { amplitude : f64,
+             period : f64 },
+}
+
+#[derive(Clone,Serialize,Deserialize)]
+pub enum Changepoints {
+  // Changes happen at the ticks of the environment with these
+  // indices (the steps, with the default clock).
+  Fixed(Vec<usize>),
+  // Changes happen at each tick with this probability, so the
+  // time between changes follows a geometric distribution.
+  Hazard(f64),
+}
+
+// Heavy-tailed noise, centered on zero.
+#[derive(Clone,Serialize,Deserialize)]
+pub enum HeavyTail {
+  // Student t with dof degrees of freedom: the moments of order
+  // dof and more are infinite.
+  StudentT { dof : f64 },
+  // Pareto of scale 1, minus its mean. The shape must be larger
+  // than 1, and the variance is infinite up to 2.
+  Pareto { shape : f64 },
+  // Symmetric alpha-stable of scale 1, with alpha in (0,2]. There
+  // is no mean for alpha at most 1, and zero is then the median.
+  Stable { alpha : f64 },
+}
+
+impl HeavyTail {
+
+  pub(crate) fn sample<T : Rng>(&self, rng : &mut T) -> f64 {
+    match *self {
+      HeavyTail::StudentT {dof} =>
+        StudentT::new(dof).unwrap().sample(rng),
+      HeavyTail::Pareto {shape} => {
+        assert!(shape > 1.0, "The Pareto shape must be larger than 1.");
+        Pareto::new(1.0,shape).unwrap().sample(rng) - shape/(shape - 1.0)
+      },
+      // Chambers-Mallows-Stuck method.
+      HeavyTail::Stable {alpha} => {
+        let angle = rng.gen_range(-PI/2.0,PI/2.0);
+        if (alpha - 1.0).abs() < f64::EPSILON {
+          return angle.tan();
+        }
+        let exp : f64 = rng.sample(Exp1);
+        (alpha*angle).sin() / angle.cos().powf(1.0/alpha)
+          * (((1.0 - alpha)*angle).cos() / exp).powf((1.0 - alpha)/alpha)
+      },
+    }
+  }
+}
+
+// Load a log of (lever, reward) pairs from a CSV file, with one pair
+// per line and an optional header. Levers must be below nb_levers.
+pub fn load_replay_log<P : AsRef<Path>>(path : P, nb_levers : usize) -> io::Result<Vec<(usize,f64)>> {
+  let invalid = |nb : usize, msg : &str|
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", nb+1, msg));
+  let mut log = Vec::new();
+  for (nb,line) in BufReader::new(File::open(path)?).lines().enumerate() {
+    let line = line?;
+    let fields : Vec<&str> = line.split(',').map(|x| x.trim()).collect();
+    if fields.iter().all(|x| x.is_empty()) {
+      continue;
+    }
+    // A header names the columns, so none of its fields is a number.
+    if nb == 0 && fields.iter().all(|x| x.parse::<f64>().is_err()) {
+      continue;
+    }
+    match (fields.len(), fields[0].parse::<usize>(), fields.get(1).map(|x| x.parse::<f64>())) {
+      (2,Ok(lever),Some(Ok(_))) if lever >= nb_levers =>
+        return Err(invalid(nb, &format!("lever {} is not below {}", lever, nb_levers))),
+      (2,Ok(lever),Some(Ok(reward))) => log.push((lever,reward)),
+      _ => return Err(invalid(nb, "expected lever,reward")),
+    }
+  }
+  Ok(log)
 }
 
 pub(crate) fn create_bandit<T : Rng>(init_data : &BanditInit, rng : &mut T) -> BanditEnum {
@@ -23,15 +148,35 @@ pub(crate) fn create_bandit<T : Rng>(init_data : &BanditInit, rng : &mut T) -> B
     BanditInit::StationaryInit {nb_levers,init_vals} =>
       BanditStationary::new(nb_levers,init_vals,rng).into(),
     BanditInit::NonStationaryInit {nb_levers,init_vals,walk} =>
-      BanditNonStationary::new(nb_levers,init_vals,walk).into(),
+      BanditNonStationary::new(vec![init_vals.0;nb_levers],
+                               init_vals.1,
+                               &DriftModel::RandomWalk {walk},
+                               rng).into(),
+    BanditInit::DriftingInit {nb_levers,init_vals,ref drift} => {
+      let init_distrib = Normal::new(init_vals.0,init_vals.1).unwrap();
+      BanditNonStationary::new(init_distrib.sample_iter(&mut *rng).take(nb_levers).collect(),
+                               init_vals.1,
+                               drift,
+                               rng).into()
+    },
+    BanditInit::PiecewiseStationaryInit {nb_levers,init_vals,ref changes} =>
+      BanditPiecewise::new(nb_levers,init_vals,changes.clone(),rng).into(),
+    BanditInit::HeavyTailedInit {nb_levers,init_vals,ref noise} =>
+      BanditHeavyTailed::new(nb_levers,init_vals,noise.clone(),rng).into(),
+    BanditInit::ReplayInit {nb_levers,ref log} =>
+      BanditReplay::new(nb_levers,log,rng).into(),
   }
 }
 
 #[enum_dispatch]
-#[derive(Clone)]
+#[derive(Clone,Serialize,Deserialize)]
+#[allow(clippy::enum_variant_names)]
 pub(crate) enum BanditEnum {
   BanditStationary,
   BanditNonStationary,
+  BanditPiecewise,
+  BanditHeavyTailed,
+  BanditReplay,
 }
 
 #[enum_dispatch(BanditEnum)]
@@ -41,29 +186,54 @@ pub(crate) trait Bandit : Clone + Send {
 
   // Get set of optimal levers.
   fn is_optimal(&self, lever : usize) -> bool;
+
+  // Check if the lever can be used at this step. Only a replay
+  // of logged data refuses levers, when they differ from the one
+  // in the log.
+  fn accepts(&mut self, _lever : usize) -> bool {
+    true
+  }
+
+  // Check if the problem ran out of rewards to give.
+  fn is_exhausted(&self) -> bool {
+    false
+  }
+
+  // Check if the levers changed abruptly at the last tick.
+  fn is_changepoint(&self) -> bool {
+    false
+  }
+
+  // Move the environment forward by one unit of time. This is
+  // independent from the pulls of levers.
+  fn tick<T: Rng>(&mut self, _rng : &mut T) {}
 }
 
 // Implementatio of a stationary bandit instance, where
 // nb_levers levers are initialized at according to a normal distribution.
-#[derive(Clone)]
+#[derive(Clone,Serialize,Deserialize)]
 pub(crate) struct BanditStationary {
-  levers : Vec<Normal<f64>>,
+  means : Vec<f64>,
+  std : f64,
   optimals : HashSet<usize>,
 }
 
 impl BanditStationary {
 
   pub(crate) fn new<T: Rng>(nb_levers : usize, init_vals : (f64,f64), rng : &mut T) -> Self {
-    let init_distrib = Normal::new(init_vals.0,init_vals.1).unwrap();
-    let (levers,optimals) : (Vec<Normal<f64>>,Vec<f64>) =
-      (0..nb_levers).map(|_| {
-                      let mean = init_distrib.sample(rng);
-                      (Normal::new(mean, init_vals.1).unwrap(),mean)
-                    })
-                    .unzip();
+    let means : Vec<f64> = Normal::new(init_vals.0,init_vals.1).unwrap()
+                                                               .sample_iter(&mut *rng)
+                                                               .take(nb_levers)
+                                                               .collect();
+    BanditStationary::with_means(means,init_vals.1)
+  }
+
+  // Levers with these means, and rewards of standard deviation std.
+  pub(crate) fn with_means(means : Vec<f64>, std : f64) -> Self {
     BanditStationary {
-      levers,
-      optimals : HashSet::from_iter(helper::indices_max(&optimals)),
+      optimals : HashSet::from_iter(helper::indices_max(&means)),
+      means,
+      std,
     }
   }
 }
@@ -71,7 +241,9 @@ impl BanditStationary {
 impl Bandit for BanditStationary {
 
   fn use_lever<T: Rng>(&mut self, lever: usize, rng: &mut T) -> f64 {
-    self.levers[lever].sample(rng)
+    Normal::new(self.means[lever],self.std)
+           .unwrap()
+           .sample(rng)
   }
 
   fn is_optimal(&self, lever : usize) -> bool {
@@ -80,38 +252,109 @@ impl Bandit for BanditStationary {
 
 }
 
-// Implementation of a nonstationary bandit problems, where
-// levers start with the same mean and move according to a random
-// walk at each step.
-#[derive(Clone)]
+// Implementation of a nonstationary bandit problems, where the
+// means of the levers move according to a drift model at each tick.
+#[derive(Clone,Serialize,Deserialize)]
 pub(crate) struct BanditNonStationary {
   levers : Vec<f64>,
   // Only one std, because only the means move according to
-  // the random walk; the standard deviation stays the same.
+  // the drift; the standard deviation stays the same.
   std : f64,
-  walks : Vec<Normal<f64>>,
+  drift : Drift,
+  time : usize,
   optimals : HashSet<usize>,
 }
 
+// Drift model, with the walk of each lever.
+#[derive(Clone,Serialize,Deserialize)]
+enum Drift {
+  Walk { walks : Vec<(f64,f64)>,
+         bounds : Option<(f64,f64)> },
+  OrnsteinUhlenbeck { theta : f64,
+                      mean : f64,
+                      sigma : f64 },
+  Periodic { amplitude : f64,
+             period : f64,
+             base : Vec<f64>,
+             phases : Vec<f64> },
+}
+
+// Fold x into [low,high] by reflecting it on the bounds. Reflections
+// repeat with a period of twice the width of the interval.
+fn reflect(x : f64, (low,high) : (f64,f64)) -> f64 {
+  let period = 2.0*(high - low);
+  let x = (x - low).rem_euclid(period);
+  low + if x > high - low { period - x } else { x }
+}
+
 impl BanditNonStationary {
 
-  pub(crate) fn new(nb_levers : usize, init_vals : (f64,f64), walk: (f64,f64)) -> Self {
-    BanditNonStationary {
-      levers : vec![init_vals.0; nb_levers],
-      std : init_vals.1,
-      walks : (0..nb_levers).map(|_| Normal::new(walk.0,walk.1).unwrap())
-                            .collect(),
-      optimals : (0..nb_levers).collect(),
-    }
+  pub(crate) fn new<T: Rng>(init_means : Vec<f64>,
+                            std : f64,
+                            drift : &DriftModel,
+                            rng : &mut T) -> Self {
+    let nb_levers = init_means.len();
+    let drift = match *drift {
+      DriftModel::RandomWalk {walk} =>
+        Drift::Walk { walks : vec![walk;nb_levers],
+                      bounds : None },
+      DriftModel::Reflected {walk,bounds} => {
+        assert!(bounds.0 < bounds.1, "The lower bound must be below the upper bound.");
+        Drift::Walk { walks : vec![walk;nb_levers],
+                      bounds : Some(bounds) }
+      },
+      DriftModel::PerLever {ref walks} => {
+        assert_eq!(walks.len(), nb_levers, "One walk is needed for each lever.");
+        Drift::Walk { walks : walks.clone(),
+                      bounds : None }
+      },
+      DriftModel::OrnsteinUhlenbeck {theta,mean,sigma} =>
+        Drift::OrnsteinUhlenbeck { theta,
+                                   mean,
+                                   sigma },
+      DriftModel::Periodic {amplitude,period} =>
+        Drift::Periodic { amplitude,
+                          period,
+                          base : init_means.clone(),
+                          phases : (0..nb_levers).map(|_| rng.gen_range(0.0,2.0*PI))
+                                                 .collect() },
+    };
+    let mut bandit = BanditNonStationary {
+      levers : init_means,
+      std,
+      drift,
+      time : 0,
+      optimals : HashSet::new(),
+    };
+    bandit.set_levers(|_,drift,lever,nb| match drift {
+      Drift::Walk {bounds : Some(bounds), ..} => reflect(lever,*bounds),
+      Drift::Periodic {amplitude,ref phases,..} => lever + amplitude*phases[nb].sin(),
+      _ => lever,
+    }, rng);
+    bandit
   }
 
   fn update<T: Rng>(&mut self, rng : &mut T) {
-    self.levers =
-      self.levers.iter()
-           .zip(self.walks.iter())
-           .map(|(lever,walk)| lever + walk.sample(rng))
-           .collect();
-    self.optimals.clear();
+    self.time += 1;
+    let time = self.time as f64;
+    self.set_levers(|rng,drift,lever,nb| match drift {
+      Drift::Walk {ref walks, bounds} => {
+        let lever = lever + Normal::new(walks[nb].0,walks[nb].1).unwrap().sample(rng);
+        bounds.map_or(lever,|bounds| reflect(lever,bounds))
+      },
+      Drift::OrnsteinUhlenbeck {theta,mean,sigma} =>
+        lever + theta*(mean - lever) + Normal::new(0.0,*sigma).unwrap().sample(rng),
+      Drift::Periodic {amplitude,period,ref base,ref phases} =>
+        base[nb] + amplitude*(2.0*PI*time/period + phases[nb]).sin(),
+    }, rng);
+  }
+
+  // Give a new value to each mean, and update the optimal levers.
+  fn set_levers<T,F>(&mut self, new_value : F, rng : &mut T)
+    where T : Rng, F : Fn(&mut T,&Drift,f64,usize) -> f64 {
+    for nb in 0..self.levers.len() {
+      self.levers[nb] = new_value(rng,&self.drift,self.levers[nb],nb);
+    }
     self.optimals = HashSet::from_iter(helper::indices_max(&self.levers));
   }
 }
@@ -119,16 +362,300 @@ impl BanditNonStationary {
 impl Bandit for BanditNonStationary {
 
   fn use_lever<T: Rng>(&mut self, lever: usize, rng: &mut T) -> f64 {
-    let result = Normal::new(self.levers[lever],self.std)
-             .unwrap()
-             .sample(rng);
+    Normal::new(self.levers[lever],self.std)
+           .unwrap()
+           .sample(rng)
+  }
+
+  fn is_optimal(&self, lever : usize) -> bool {
+    self.optimals.contains(&lever)
+  }
+
+  fn tick<T: Rng>(&mut self, rng : &mut T) {
     self.update(rng);
-    result
+  }
+}
 
+// Implementation of a piecewise stationary bandit, where the means
+// of the levers are sampled again at each changepoint.
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct BanditPiecewise {
+  init_vals : (f64,f64),
+  means : Vec<f64>,
+  changes : Changepoints,
+  // Number of ticks done.
+  time : usize,
+  changed : bool,
+  optimals : HashSet<usize>,
+}
+
+impl BanditPiecewise {
+
+  pub(crate) fn new<T: Rng>(nb_levers : usize,
+                            init_vals : (f64,f64),
+                            changes : Changepoints,
+                            rng : &mut T) -> Self {
+    let mut bandit = BanditPiecewise {
+      init_vals,
+      means : vec![0.0;nb_levers],
+      changes,
+      time : 0,
+      changed : false,
+      optimals : HashSet::new(),
+    };
+    bandit.resample(rng);
+    bandit.changed = bandit.changes_at(0,rng);
+    bandit
+  }
+
+  fn resample<T: Rng>(&mut self, rng : &mut T) {
+    let init_distrib = Normal::new(self.init_vals.0,self.init_vals.1).unwrap();
+    self.means.iter_mut().for_each(|mean| *mean = init_distrib.sample(rng));
+    self.optimals = HashSet::from_iter(helper::indices_max(&self.means));
+  }
+
+  fn changes_at<T: Rng>(&self, time : usize, rng : &mut T) -> bool {
+    match self.changes {
+      Changepoints::Fixed(ref times) => times.contains(&time),
+      Changepoints::Hazard(proba) => time > 0 && rng.gen_bool(proba),
+    }
+  }
+
+  fn update<T: Rng>(&mut self, rng : &mut T) {
+    self.time += 1;
+    self.changed = self.changes_at(self.time,rng);
+    if self.changed {
+      self.resample(rng);
+    }
+  }
+}
+
+impl Bandit for BanditPiecewise {
+
+  fn use_lever<T: Rng>(&mut self, lever: usize, rng: &mut T) -> f64 {
+    Normal::new(self.means[lever],self.init_vals.1)
+           .unwrap()
+           .sample(rng)
+  }
+
+  fn is_optimal(&self, lever : usize) -> bool {
+    self.optimals.contains(&lever)
+  }
+
+  fn is_changepoint(&self) -> bool {
+    self.changed
+  }
+
+  fn tick<T: Rng>(&mut self, rng : &mut T) {
+    self.update(rng);
+  }
+}
+
+// Stationary bandit with heavy-tailed rewards.
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct BanditHeavyTailed {
+  means : Vec<f64>,
+  scale : f64,
+  noise : HeavyTail,
+  optimals : HashSet<usize>,
+}
+
+impl BanditHeavyTailed {
+
+  pub(crate) fn new<T: Rng>(nb_levers : usize,
+                            init_vals : (f64,f64),
+                            noise : HeavyTail,
+                            rng : &mut T) -> Self {
+    let means : Vec<f64> = Normal::new(init_vals.0,init_vals.1).unwrap()
+                                                               .sample_iter(&mut *rng)
+                                                               .take(nb_levers)
+                                                               .collect();
+    BanditHeavyTailed {
+      optimals : HashSet::from_iter(helper::indices_max(&means)),
+      means,
+      scale : init_vals.1,
+      noise,
+    }
+  }
+}
+
+impl Bandit for BanditHeavyTailed {
+
+  fn use_lever<T: Rng>(&mut self, lever: usize, rng: &mut T) -> f64 {
+    self.means[lever] + self.scale*self.noise.sample(rng)
+  }
+
+  fn is_optimal(&self, lever : usize) -> bool {
+    self.optimals.contains(&lever)
+  }
+}
+
+// Implementation of the replay method on logged data: the log is
+// read in a random order, and a step only happens when the policy
+// chooses the lever of the current entry. Entries with another
+// lever are discarded. Optimal levers are the ones with the best
+// average reward in the log.
+#[derive(Clone,Serialize,Deserialize)]
+pub(crate) struct BanditReplay {
+  log : Arc<Vec<(usize,f64)>>,
+  order : Vec<usize>,
+  current : usize,
+  optimals : HashSet<usize>,
+}
+
+impl BanditReplay {
+
+  pub(crate) fn new<T: Rng>(nb_levers : usize, log : &Arc<Vec<(usize,f64)>>, rng : &mut T) -> Self {
+    let mut sums = vec![0.0;nb_levers];
+    let mut counts = vec![0.0;nb_levers];
+    for &(lever,reward) in log.iter() {
+      assert!(lever < nb_levers, "The log has lever {}, but only {} levers.", lever, nb_levers);
+      sums[lever] += reward;
+      counts[lever] += 1.0;
+    }
+    let means : Vec<f64> =
+      sums.iter()
+          .zip(counts.iter())
+          .map(|(sum,count)| if *count > 0.0 { sum / count } else { f64::NEG_INFINITY })
+          .collect();
+    let mut order : Vec<usize> = (0..log.len()).collect();
+    order.shuffle(rng);
+    BanditReplay {
+      log : Arc::clone(log),
+      order,
+      current : 0,
+      optimals : HashSet::from_iter(helper::indices_max(&means)),
+    }
+  }
+
+  fn entry(&self) -> (usize,f64) {
+    self.log[self.order[self.current]]
+  }
+}
+
+impl Bandit for BanditReplay {
+
+  fn use_lever<T: Rng>(&mut self, lever: usize, _rng: &mut T) -> f64 {
+    let (logged,reward) = self.entry();
+    assert_eq!(lever, logged, "Replay can only use the logged lever.");
+    self.current += 1;
+    reward
   }
 
   fn is_optimal(&self, lever : usize) -> bool {
     self.optimals.contains(&lever)
   }
 
+  fn accepts(&mut self, lever : usize) -> bool {
+    if self.entry().0 == lever {
+      true
+    } else {
+      self.current += 1;
+      false
+    }
+  }
+
+  fn is_exhausted(&self) -> bool {
+    self.current >= self.order.len()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_replay() {
+        let log = Arc::new(vec![(0,1.0),(1,0.0),(0,1.0),(1,0.5)]);
+        let mut bandit = BanditReplay::new(2,&log,&mut StdRng::seed_from_u64(0));
+        assert!(bandit.is_optimal(0));
+        let mut total = 0.0;
+        while !bandit.is_exhausted() {
+            if bandit.accepts(1) {
+                total += bandit.use_lever(1,&mut StdRng::seed_from_u64(0));
+            }
+        }
+        assert_eq!(total,0.5);
+    }
+
+    #[test]
+    fn test_load_replay_log() {
+        let path = std::env::temp_dir().join("bandit_rs_replay_log.csv");
+        std::fs::write(&path,"arm,reward\n0,1.5\n2, -0.5\n\n").unwrap();
+        assert_eq!(load_replay_log(&path,3).unwrap(),vec![(0,1.5),(2,-0.5)]);
+        // The lever 2 does not exist.
+        assert!(load_replay_log(&path,2).is_err());
+        std::fs::write(&path,"0,1.5\n2\n").unwrap();
+        assert!(load_replay_log(&path,3).is_err());
+        // A malformed first line is not a header.
+        std::fs::write(&path,"0,abc\n1,1.5\n").unwrap();
+        assert!(load_replay_log(&path,3).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_fixed_changepoints() {
+        let est = crate::EstimatorInit::SampleAverageInit {nb_levers : 2};
+        let policy = crate::PolicyInit::EGreedyInit {nb_levers : 2, expl_proba : 0.5.into(), est : &est};
+        let problem = BanditInit::PiecewiseStationaryInit {nb_levers : 2,
+                                                           init_vals : (0.0,1.0),
+                                                           changes : Changepoints::Fixed(vec![3,7])};
+        let results = crate::run_reprod_experiments(&[policy],problem,&mut StdRng::seed_from_u64(0),2,10);
+        for steps in results[0].iter() {
+            assert_eq!(crate::changepoints(steps),vec![3,7]);
+        }
+    }
+
+    #[test]
+    fn test_drift_models() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut reflected = BanditNonStationary::new(vec![0.0,0.5],
+                                                     1.0,
+                                                     &DriftModel::Reflected {walk : (0.0,0.5),
+                                                                             bounds : (-1.0,1.0)},
+                                                     &mut rng);
+        let mut periodic = BanditNonStationary::new(vec![0.0,0.5],
+                                                    1.0,
+                                                    &DriftModel::Periodic {amplitude : 1.0,
+                                                                           period : 10.0},
+                                                    &mut rng);
+        let start = periodic.levers.clone();
+        for _ in 0..100 {
+            reflected.tick(&mut rng);
+            periodic.tick(&mut rng);
+            assert!(reflected.levers.iter().all(|x| x.abs() <= 1.0));
+        }
+        assert!(periodic.levers.iter().zip(start.iter()).all(|(x,y)| (x-y).abs() < 1e-9));
+    }
+
+    #[test]
+    fn test_reflect() {
+        assert_eq!(reflect(0.5,(0.0,1.0)),0.5);
+        assert!((reflect(1.25,(0.0,1.0)) - 0.75).abs() < 1e-12);
+        assert!((reflect(-0.25,(0.0,1.0)) - 0.25).abs() < 1e-12);
+        assert!((reflect(1e9 + 0.25,(-1.0,1.0)) - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty_bounds() {
+        let drift = DriftModel::Reflected {walk : (0.0,0.1), bounds : (1.0,1.0)};
+        BanditNonStationary::new(vec![1.0,1.0],1.0,&drift,&mut StdRng::seed_from_u64(0));
+    }
+
+    #[test]
+    fn test_heavy_tails() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for noise in [HeavyTail::StudentT {dof : 3.0},
+                      HeavyTail::Pareto {shape : 3.0},
+                      HeavyTail::Stable {alpha : 1.5}].iter() {
+            let mut samples : Vec<f64> = (0..10001).map(|_| noise.sample(&mut rng)).collect();
+            samples.sort_by(|a,b| a.partial_cmp(b).unwrap());
+            assert!(samples[0] < -2.0 || samples[10000] > 5.0);
+            // The Pareto median is below its mean.
+            assert!(samples[5000].abs() < 0.5, "{}", samples[5000]);
+        }
+    }
 }
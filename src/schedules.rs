@@ -0,0 +1,93 @@
+use serde::{Serialize,Deserialize};
+
+// Value of a parameter of a policy, like the exploration probability
+// or the temperature, as a function of the number t of updates of
+// the policy, starting at 0.
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+pub enum Schedule {
+  Constant(f64),
+  // init/(1 + rate*t)
+  Inverse {init : f64,
+           rate : f64},
+  // init/sqrt(1 + rate*t)
+  InverseSqrt {init : f64,
+               rate : f64},
+  // init*decay^t, but never below min.
+  Exponential {init : f64,
+               decay : f64,
+               min : f64},
+  // Linear interpolation between (t, value) points, sorted by t,
+  // and constant before the first and after the last.
+  PiecewiseLinear(Vec<(f64,f64)>),
+  // Value at each t, and then the last value forever.
+  Table(Vec<f64>),
+}
+
+impl Schedule {
+
+  pub fn value(&self, time : usize) -> f64 {
+    let t = time as f64;
+    match self {
+      Schedule::Constant(value) => *value,
+      Schedule::Inverse {init,rate} => init / (1.0 + rate*t),
+      Schedule::InverseSqrt {init,rate} => init / (1.0 + rate*t).sqrt(),
+      Schedule::Exponential {init,decay,min} => (init*decay.powf(t)).max(*min),
+      Schedule::PiecewiseLinear(points) => {
+        assert!(!points.is_empty(), "A piecewise linear schedule needs points.");
+        match points.iter().position(|point| point.0 > t) {
+          Some(0) => points[0].1,
+          None => points[points.len()-1].1,
+          Some(next) => {
+            let ((t0,v0),(t1,v1)) = (points[next-1],points[next]);
+            v0 + (v1 - v0)*(t - t0)/(t1 - t0)
+          },
+        }
+      },
+      Schedule::Table(values) => {
+        assert!(!values.is_empty(), "A table schedule needs values.");
+        values[time.min(values.len()-1)]
+      },
+    }
+  }
+
+  // The same schedule, with every value multiplied by factor.
+  pub(crate) fn scale(&self, factor : f64) -> Schedule {
+    match self {
+      Schedule::Constant(value) => Schedule::Constant(factor*value),
+      Schedule::Inverse {init,rate} =>
+        Schedule::Inverse {init : factor*init, rate : *rate},
+      Schedule::InverseSqrt {init,rate} =>
+        Schedule::InverseSqrt {init : factor*init, rate : *rate},
+      Schedule::Exponential {init,decay,min} =>
+        Schedule::Exponential {init : factor*init, decay : *decay, min : factor*min},
+      Schedule::PiecewiseLinear(points) =>
+        Schedule::PiecewiseLinear(points.iter().map(|(t,v)| (*t,factor*v)).collect()),
+      Schedule::Table(values) =>
+        Schedule::Table(values.iter().map(|v| factor*v).collect()),
+    }
+  }
+}
+
+impl From<f64> for Schedule {
+
+  fn from(value : f64) -> Self {
+    Schedule::Constant(value)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedules() {
+        assert_eq!(Schedule::from(0.1).value(100),0.1);
+        assert_eq!(Schedule::Inverse {init : 1.0, rate : 1.0}.value(3),0.25);
+        assert_eq!(Schedule::Exponential {init : 1.0, decay : 0.5, min : 0.2}.value(4),0.2);
+        let linear = Schedule::PiecewiseLinear(vec![(0.0,1.0),(10.0,0.0)]);
+        assert_eq!((linear.value(0),linear.value(5),linear.value(20)),(1.0,0.5,0.0));
+        let table = Schedule::Table(vec![0.3,0.2,0.1]);
+        assert_eq!((table.value(1),table.value(7)),(0.2,0.1));
+        assert_eq!(table.scale(2.0).value(0),0.6);
+    }
+}
@@ -0,0 +1,482 @@
+use crate::estimators::{EstimatorInit,InitialValues,StepSize};
+use crate::policies::{Divergence,PolicyInit};
+use crate::schedules::Schedule;
+use crate::session::{Session,SessionError};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::SocketAddr;
+
+use serde::{Serialize,Deserialize,Deserializer};
+use serde_json::json;
+use tiny_http::{Header,Method,Response};
+
+// Owned counterpart of PolicyInit, which can be sent over the wire.
+// Schedules can also be given as a bare number, for a constant.
+#[derive(Clone,Serialize,Deserialize)]
+pub enum PolicyConfig {
+  EGreedyInit {nb_levers : usize,
+               #[serde(deserialize_with = "schedule")]
+               expl_proba : Schedule,
+               est : EstimatorInit},
+  SoftmaxInit {nb_levers : usize,
+               #[serde(deserialize_with = "schedule")]
+               temperature : Schedule,
+               est : EstimatorInit},
+  UCBInit {nb_levers : usize,
+           #[serde(deserialize_with = "schedule")]
+           step : Schedule,
+           est : EstimatorInit},
+  CusumUCBInit {nb_levers : usize,
+                step : f64,
+                expl_proba : f64,
+                warmup : usize,
+                drift : f64,
+                threshold : f64,
+                est : EstimatorInit},
+  GLRklUCBInit {nb_levers : usize,
+                expl_proba : f64,
+                confidence : f64,
+                est : EstimatorInit},
+  MUCBInit {nb_levers : usize,
+            step : f64,
+            expl_proba : f64,
+            window : usize,
+            threshold : f64,
+            est : EstimatorInit},
+  ThompsonInit {nb_levers : usize,
+                est : EstimatorInit},
+  KLUCBInit {nb_levers : usize,
+             divergence : Divergence,
+             c : f64,
+             est : EstimatorInit},
+  BayesUCBInit {nb_levers : usize,
+                est : EstimatorInit},
+  UCBVInit {nb_levers : usize,
+            range : f64,
+            zeta : f64,
+            est : EstimatorInit},
+  UCBTunedInit {nb_levers : usize,
+                est : EstimatorInit},
+  MOSSInit {nb_levers : usize,
+            est : EstimatorInit},
+  ExploreThenCommitInit {nb_levers : usize,
+                         nb_explore : usize,
+                         est : EstimatorInit},
+  SuccessiveEliminationInit {nb_levers : usize,
+                             range : f64,
+                             confidence : f64,
+                             est : EstimatorInit},
+}
+
+impl PolicyConfig {
+
+  pub fn init(&self) -> PolicyInit<'_> {
+    match self {
+      PolicyConfig::EGreedyInit {nb_levers,expl_proba,est} =>
+        PolicyInit::EGreedyInit {nb_levers : *nb_levers,
+                                 expl_proba : expl_proba.clone(),
+                                 est},
+      PolicyConfig::SoftmaxInit {nb_levers,temperature,est} =>
+        PolicyInit::SoftmaxInit {nb_levers : *nb_levers,
+                                 temperature : temperature.clone(),
+                                 est},
+      PolicyConfig::UCBInit {nb_levers,step,est} =>
+        PolicyInit::UCBInit {nb_levers : *nb_levers,
+                             step : step.clone(),
+                             est},
+      PolicyConfig::CusumUCBInit {nb_levers,step,expl_proba,warmup,drift,threshold,est} =>
+        PolicyInit::CusumUCBInit {nb_levers : *nb_levers,
+                                  step : *step,
+                                  expl_proba : *expl_proba,
+                                  warmup : *warmup,
+                                  drift : *drift,
+                                  threshold : *threshold,
+                                  est},
+      PolicyConfig::GLRklUCBInit {nb_levers,expl_proba,confidence,est} =>
+        PolicyInit::GLRklUCBInit {nb_levers : *nb_levers,
+                                  expl_proba : *expl_proba,
+                                  confidence : *confidence,
+                                  est},
+      PolicyConfig::MUCBInit {nb_levers,step,expl_proba,window,threshold,est} =>
+        PolicyInit::MUCBInit {nb_levers : *nb_levers,
+                              step : *step,
+                              expl_proba : *expl_proba,
+                              window : *window,
+                              threshold : *threshold,
+                              est},
+      PolicyConfig::ThompsonInit {nb_levers,est} =>
+        PolicyInit::ThompsonInit {nb_levers : *nb_levers,
+                                  est},
+      PolicyConfig::KLUCBInit {nb_levers,divergence,c,est} =>
+        PolicyInit::KLUCBInit {nb_levers : *nb_levers,
+                               divergence : *divergence,
+                               c : *c,
+                               est},
+      PolicyConfig::BayesUCBInit {nb_levers,est} =>
+        PolicyInit::BayesUCBInit {nb_levers : *nb_levers,
+                                  est},
+      PolicyConfig::UCBVInit {nb_levers,range,zeta,est} =>
+        PolicyInit::UCBVInit {nb_levers : *nb_levers,
+                              range : *range,
+                              zeta : *zeta,
+                              est},
+      PolicyConfig::UCBTunedInit {nb_levers,est} =>
+        PolicyInit::UCBTunedInit {nb_levers : *nb_levers,
+                                  est},
+      PolicyConfig::MOSSInit {nb_levers,est} =>
+        PolicyInit::MOSSInit {nb_levers : *nb_levers,
+                              est},
+      PolicyConfig::ExploreThenCommitInit {nb_levers,nb_explore,est} =>
+        PolicyInit::ExploreThenCommitInit {nb_levers : *nb_levers,
+                                           nb_explore : *nb_explore,
+                                           est},
+      PolicyConfig::SuccessiveEliminationInit {nb_levers,range,confidence,est} =>
+        PolicyInit::SuccessiveEliminationInit {nb_levers : *nb_levers,
+                                               range : *range,
+                                               confidence : *confidence,
+                                               est},
+    }
+  }
+
+  // Check that a policy can be created from this configuration and
+  // used without panicking, and give the reason when it cannot.
+  pub fn validate(&self) -> Result<(),String> {
+    let (nb_levers,est) = match self {
+      PolicyConfig::EGreedyInit {nb_levers,expl_proba,est} => {
+        probability_schedule("expl_proba",expl_proba)?;
+        (nb_levers,est)
+      },
+      PolicyConfig::SoftmaxInit {nb_levers,temperature,est} => {
+        bounded_schedule("temperature",temperature)?;
+        (nb_levers,est)
+      },
+      PolicyConfig::UCBInit {nb_levers,step,est} => {
+        bounded_schedule("step",step)?;
+        (nb_levers,est)
+      },
+      PolicyConfig::CusumUCBInit {nb_levers,expl_proba,warmup,drift,threshold,est,..} => {
+        probability("expl_proba",*expl_proba)?;
+        positive("warmup",*warmup as f64)?;
+        positive("drift",*drift)?;
+        positive("threshold",*threshold)?;
+        (nb_levers,est)
+      },
+      PolicyConfig::GLRklUCBInit {nb_levers,expl_proba,confidence,est} => {
+        probability("expl_proba",*expl_proba)?;
+        open_probability("confidence",*confidence)?;
+        (nb_levers,est)
+      },
+      PolicyConfig::MUCBInit {nb_levers,expl_proba,window,est,..} => {
+        probability("expl_proba",*expl_proba)?;
+        positive("window",*window as f64)?;
+        (nb_levers,est)
+      },
+      PolicyConfig::KLUCBInit {nb_levers,divergence,est,..} => {
+        if let Divergence::Gaussian {variance} = divergence {
+          positive("variance",*variance)?;
+        }
+        (nb_levers,est)
+      },
+      PolicyConfig::SuccessiveEliminationInit {nb_levers,range,confidence,est} => {
+        positive("range",*range)?;
+        open_probability("confidence",*confidence)?;
+        (nb_levers,est)
+      },
+      PolicyConfig::ThompsonInit {nb_levers,est}
+        | PolicyConfig::BayesUCBInit {nb_levers,est}
+        | PolicyConfig::UCBVInit {nb_levers,est,..}
+        | PolicyConfig::UCBTunedInit {nb_levers,est}
+        | PolicyConfig::MOSSInit {nb_levers,est}
+        | PolicyConfig::ExploreThenCommitInit {nb_levers,est,..} => (nb_levers,est),
+    };
+    positive("nb_levers",*nb_levers as f64)?;
+    let est_levers = validate_estimator(est)?;
+    if est_levers != *nb_levers {
+      return Err(format!("the estimator has {} levers instead of {}", est_levers, nb_levers));
+    }
+    Ok(())
+  }
+}
+
+// Read a schedule, or a bare number as a constant schedule.
+fn schedule<'de,D : Deserializer<'de>>(deserializer : D) -> Result<Schedule,D::Error> {
+  #[derive(Deserialize)]
+  #[serde(untagged)]
+  enum Value {
+    Constant(f64),
+    Schedule(Schedule),
+  }
+  Ok(match Value::deserialize(deserializer)? {
+    Value::Constant(value) => Schedule::Constant(value),
+    Value::Schedule(schedule) => schedule,
+  })
+}
+
+// Steps which are positive and finite from the first update.
+fn step_size(steps : &StepSize) -> Result<(),String> {
+  match *steps {
+    StepSize::Power {exponent} => positive("exponent",exponent),
+    StepSize::Rational {alpha,beta} => {
+      positive("alpha",alpha)?;
+      if beta < 0.0 {
+        return Err("beta must not be negative".to_string());
+      }
+      Ok(())
+    },
+    StepSize::Harmonic {scale} => positive("scale",scale),
+    StepSize::Kesten {alpha,beta} => {
+      positive("alpha",alpha)?;
+      positive("beta",beta)
+    },
+  }
+}
+
+// Check the parameters of an estimator, and give its number of levers.
+fn validate_estimator(est : &EstimatorInit) -> Result<usize,String> {
+  let init_values = |nb_levers : usize, init : &InitialValues| match init {
+    InitialValues::PerLever(values) if values.len() != nb_levers =>
+      Err(format!("{} initial values for {} levers", values.len(), nb_levers)),
+    _ => Ok(()),
+  };
+  match *est {
+    EstimatorInit::SampleAverageInit {nb_levers} => Ok(nb_levers),
+    EstimatorInit::ScheduledStepInit {nb_levers,steps,ref init} => {
+      init_values(nb_levers,init)?;
+      step_size(&steps)?;
+      Ok(nb_levers)
+    },
+    EstimatorInit::ConstantStepInit {nb_levers,step} => {
+      positive("step",step)?;
+      Ok(nb_levers)
+    },
+    EstimatorInit::PriorSampleAverageInit {nb_levers,ref init,pseudo_counts} => {
+      init_values(nb_levers,init)?;
+      if pseudo_counts < 0.0 {
+        return Err("pseudo_counts must not be negative".to_string());
+      }
+      Ok(nb_levers)
+    },
+    EstimatorInit::PriorConstantStepInit {nb_levers,step,ref init}
+      | EstimatorInit::UnbiasedConstantStepInit {nb_levers,step,ref init} => {
+      init_values(nb_levers,init)?;
+      positive("step",step)?;
+      Ok(nb_levers)
+    },
+    EstimatorInit::NormalInit {nb_levers,prior_var,noise_var,..} => {
+      positive("prior_var",prior_var)?;
+      positive("noise_var",noise_var)?;
+      Ok(nb_levers)
+    },
+    EstimatorInit::NormalGammaInit {nb_levers,strength,shape,rate,..} => {
+      positive("strength",strength)?;
+      positive("shape",shape)?;
+      positive("rate",rate)?;
+      Ok(nb_levers)
+    },
+    EstimatorInit::BetaBernoulliInit {nb_levers,alpha,beta} => {
+      positive("alpha",alpha)?;
+      positive("beta",beta)?;
+      Ok(nb_levers)
+    },
+    EstimatorInit::GammaPoissonInit {nb_levers,shape,rate} => {
+      positive("shape",shape)?;
+      positive("rate",rate)?;
+      Ok(nb_levers)
+    },
+    EstimatorInit::SlidingWindowInit {nb_levers,window} => {
+      positive("window",window as f64)?;
+      Ok(nb_levers)
+    },
+    EstimatorInit::DiscountedInit {nb_levers,discount} => {
+      positive("discount",discount)?;
+      probability("discount",discount)?;
+      Ok(nb_levers)
+    },
+    EstimatorInit::MedianOfMeansInit {nb_levers,nb_blocks} => {
+      positive("nb_blocks",nb_blocks as f64)?;
+      Ok(nb_levers)
+    },
+    EstimatorInit::TruncatedMeanInit {nb_levers,epsilon,moment,confidence} => {
+      positive("epsilon",epsilon)?;
+      positive("moment",moment)?;
+      open_probability("confidence",confidence)?;
+      Ok(nb_levers)
+    },
+    EstimatorInit::CatoniInit {nb_levers,variance,confidence,window} => {
+      positive("variance",variance)?;
+      positive("window",window as f64)?;
+      open_probability("confidence",confidence)?;
+      Ok(nb_levers)
+    },
+    EstimatorInit::KalmanInit {nb_levers,init_var,walk_var,obs_var,..} => {
+      positive("init_var",init_var)?;
+      positive("obs_var",obs_var)?;
+      if walk_var < 0.0 {
+        return Err("walk_var must not be negative".to_string());
+      }
+      Ok(nb_levers)
+    },
+  }
+}
+
+fn positive(name : &str, value : f64) -> Result<(),String> {
+  if value > 0.0 {
+    Ok(())
+  } else {
+    Err(format!("{} must be positive", name))
+  }
+}
+
+fn probability(name : &str, value : f64) -> Result<(),String> {
+  if (0.0..=1.0).contains(&value) {
+    Ok(())
+  } else {
+    Err(format!("{} must be in [0,1]", name))
+  }
+}
+
+fn open_probability(name : &str, value : f64) -> Result<(),String> {
+  if value > 0.0 && value < 1.0 {
+    Ok(())
+  } else {
+    Err(format!("{} must be in (0,1)", name))
+  }
+}
+
+// Smallest and largest values of a schedule over all times, if it is
+// bounded. The bounds are not always reached.
+fn schedule_bounds(schedule : &Schedule) -> Option<(f64,f64)> {
+  let extremes = |values : &mut dyn Iterator<Item=f64>| {
+    values.fold(None,|acc : Option<(f64,f64)>,x| match acc {
+      None => Some((x,x)),
+      Some((low,high)) => Some((low.min(x),high.max(x))),
+    })
+  };
+  match schedule {
+    Schedule::Constant(value) => Some((*value,*value)),
+    Schedule::Inverse {init,rate} | Schedule::InverseSqrt {init,rate} if *rate >= 0.0 =>
+      Some((init.min(0.0),init.max(0.0))),
+    Schedule::Exponential {init,decay,min} if (0.0..=1.0).contains(decay) =>
+      Some((init.min(0.0).max(*min),init.max(0.0).max(*min))),
+    Schedule::PiecewiseLinear(points) => extremes(&mut points.iter().map(|point| point.1)),
+    Schedule::Table(values) => extremes(&mut values.iter().cloned()),
+    _ => None,
+  }
+}
+
+fn bounded_schedule(name : &str, schedule : &Schedule) -> Result<(f64,f64),String> {
+  schedule_bounds(schedule).ok_or(format!("{} must stay bounded", name))
+}
+
+fn probability_schedule(name : &str, schedule : &Schedule) -> Result<(),String> {
+  let (low,high) = bounded_schedule(name,schedule)?;
+  probability(name,low)?;
+  probability(name,high)
+}
+
+#[derive(Deserialize)]
+struct Reward {
+  id : u64,
+  reward : f64,
+}
+
+// HTTP service hosting named sessions. The endpoints are:
+// - POST   /sessions/<name>            create a session from a valid PolicyConfig
+// - DELETE /sessions/<name>            drop a session
+// - POST   /sessions/<name>/decisions  choose a lever, gives {id, lever}
+// - POST   /sessions/<name>/rewards    report {id, reward} of a decision
+// - GET    /sessions/<name>/estimates  current estimates of all levers
+pub struct Server {
+  http : tiny_http::Server,
+  sessions : HashMap<String,Session>,
+}
+
+impl Server {
+
+  pub fn bind(addr : &str) -> Result<Self,Box<dyn Error + Send + Sync>> {
+    Ok(Server {
+      http : tiny_http::Server::http(addr)?,
+      sessions : HashMap::new(),
+    })
+  }
+
+  pub fn local_addr(&self) -> SocketAddr {
+    self.http.server_addr().to_ip().unwrap()
+  }
+
+  // Answer requests until the process is stopped.
+  pub fn run(mut self) {
+    for mut request in self.http.incoming_requests() {
+      let mut body = String::new();
+      let (status,value) = match request.as_reader().read_to_string(&mut body) {
+        Ok(_) => route(&mut self.sessions,request.method(),request.url(),&body),
+        Err(e) => error(400,&e.to_string()),
+      };
+      let response =
+        Response::from_string(value.to_string())
+                 .with_status_code(status)
+                 .with_header(Header::from_bytes(&b"Content-Type"[..],
+                                                 &b"application/json"[..]).unwrap());
+      let _ = request.respond(response);
+    }
+  }
+}
+
+fn error(status : u16, msg : &str) -> (u16,serde_json::Value) {
+  (status, json!({ "error" : msg }))
+}
+
+fn route(sessions : &mut HashMap<String,Session>,
+         method : &Method,
+         url : &str,
+         body : &str) -> (u16,serde_json::Value) {
+  let path : Vec<&str> = url.trim_matches('/').split('/').collect();
+  match (method,&path[..]) {
+    (Method::Post,["sessions",name]) => {
+      if sessions.contains_key(*name) {
+        return error(409,"session already exists");
+      }
+      match serde_json::from_str::<PolicyConfig>(body) {
+        Ok(config) => match config.validate() {
+          Ok(()) => {
+            sessions.insert(name.to_string(),Session::new(&config.init()));
+            (201, json!({}))
+          },
+          Err(msg) => error(400,&msg),
+        },
+        Err(e) => error(400,&e.to_string()),
+      }
+    },
+    (Method::Delete,["sessions",name]) =>
+      match sessions.remove(*name) {
+        Some(_) => (200, json!({})),
+        None => error(404,"unknown session"),
+      },
+    (Method::Post,["sessions",name,"decisions"]) =>
+      match sessions.get_mut(*name) {
+        Some(session) => (200, serde_json::to_value(session.choose()).unwrap()),
+        None => error(404,"unknown session"),
+      },
+    (Method::Post,["sessions",name,"rewards"]) => {
+      let session = match sessions.get_mut(*name) {
+        Some(session) => session,
+        None => return error(404,"unknown session"),
+      };
+      match serde_json::from_str::<Reward>(body) {
+        Ok(Reward {id,reward}) =>
+          match session.report(id,reward) {
+            Ok(()) => (200, json!({})),
+            Err(e @ SessionError::UnknownDecision(_)) => error(404,&e.to_string()),
+          },
+        Err(e) => error(400,&e.to_string()),
+      }
+    },
+    (Method::Get,["sessions",name,"estimates"]) =>
+      match sessions.get(*name) {
+        Some(session) => (200, json!({ "estimates" : session.estimates() })),
+        None => error(404,"unknown session"),
+      },
+    _ => error(404,"unknown endpoint"),
+  }
+}
@@ -0,0 +1,199 @@
+use crate::estimators::Estimator;
+use crate::policies::{Policy,PolicyInit,PolicyEnum,create_policy};
+use crate::snapshot::SessionSnapshot;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use rand::Rng;
+use serde::{Serialize,Deserialize};
+
+// A lever chosen by the policy of a session. The id is used
+// to report the reward of this decision later on.
+#[derive(Clone,Copy,Debug,PartialEq,Serialize,Deserialize)]
+pub struct Decision {
+  pub id : u64,
+  pub lever : usize,
+  // Probability the policy gave to this lever. None when it was
+  // not recorded (see Session::record_probabilities).
+  pub propensity : Option<f64>,
+}
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum SessionError {
+  // No outstanding decision with this id: either it was never
+  // issued, or its reward was already reported.
+  UnknownDecision(u64),
+}
+
+impl fmt::Display for SessionError {
+
+  fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      SessionError::UnknownDecision(id) =>
+        write!(f, "no outstanding decision with id {}", id),
+    }
+  }
+}
+
+impl Error for SessionError {}
+
+// Online use of a policy: instead of pulling levers of a simulated
+// bandit, decisions are handed out one at a time, and their rewards
+// are reported whenever they become available, in any order.
+#[derive(Clone)]
+pub struct Session {
+  policy : PolicyEnum,
+  next_id : u64,
+  // Every decision still waiting for its reward.
+  pending : HashMap<u64,Decision>,
+  // Whether to record probabilities the policy can only estimate.
+  record : bool,
+}
+
+impl Session {
+
+  pub fn new(policy : &PolicyInit) -> Self {
+    Session {
+      policy : create_policy(policy,None),
+      next_id : 0,
+      pending : HashMap::new(),
+      record : false,
+    }
+  }
+
+  // Record the propensity of every decision, even when the policy
+  // can only estimate it by sampling decisions, which is slow.
+  // Otherwise, it is only recorded when it is exact.
+  pub fn record_probabilities(mut self, record : bool) -> Self {
+    self.record = record;
+    self
+  }
+
+  // Choose a lever, using the thread local random generator.
+  pub fn choose(&mut self) -> Decision {
+    self.choose_reprod(&mut rand::thread_rng())
+  }
+
+  // Choose a lever, using the given random generator.
+  pub fn choose_reprod<T : Rng>(&mut self, rng : &mut T) -> Decision {
+    // Every decision after the first one is one tick of the environment.
+    if self.next_id > 0 {
+      self.policy.tick();
+    }
+    let probas = if self.record || self.policy.exact_probabilities() {
+      Some(self.policy.probabilities(rng))
+    } else {
+      None
+    };
+    let lever = self.policy.decide(rng);
+    let decision = Decision {
+      id : self.next_id,
+      lever,
+      propensity : probas.map(|probas| probas[lever]),
+    };
+    self.next_id += 1;
+    self.pending.insert(decision.id,decision);
+    decision
+  }
+
+  // Give the reward of an outstanding decision to the policy.
+  pub fn report(&mut self, decision_id : u64, reward : f64) -> Result<(),SessionError> {
+    let decision = self.pending.remove(&decision_id)
+                               .ok_or(SessionError::UnknownDecision(decision_id))?;
+    self.policy.update(decision.lever,reward);
+    Ok(())
+  }
+
+  // Current estimates of the policy for all levers.
+  pub fn estimates(&self) -> Vec<f64> {
+    self.policy.estimates()
+  }
+
+  // Variance of the mean reward of each lever under the posterior
+  // of the estimator, zero for estimators which are not bayesian.
+  pub fn variances(&self) -> Vec<f64> {
+    let estimator = self.policy.estimator();
+    (0..estimator.nb_levers()).map(|lever| estimator.variance(lever))
+                              .collect()
+  }
+
+  // Interval containing the mean reward of each lever with
+  // probability level, like 0.95. Estimators which are not bayesian
+  // give a confidence interval instead.
+  pub fn credible_intervals(&self, level : f64) -> Vec<(f64,f64)> {
+    let estimator = self.policy.estimator();
+    (0..estimator.nb_levers()).map(|lever| estimator.credible_interval(lever,level))
+                              .collect()
+  }
+
+  // Probability of choosing each lever at the next decision,
+  // using the thread local random generator if it must be estimated.
+  pub fn probabilities(&self) -> Vec<f64> {
+    self.policy.probabilities(&mut rand::thread_rng())
+  }
+
+  // Number of decisions whose reward was not reported yet.
+  pub fn nb_pending(&self) -> usize {
+    self.pending.len()
+  }
+
+  // Save the state of the session, to resume it later.
+  pub fn snapshot(&self) -> SessionSnapshot {
+    SessionSnapshot::new(self.policy.clone(),
+                         self.next_id,
+                         self.pending.values().cloned().collect(),
+                         self.record)
+  }
+
+  // Resume a session from a saved state. Decisions pending when the
+  // snapshot was taken can still be reported.
+  pub fn restore(snapshot : SessionSnapshot) -> Self {
+    Session {
+      policy : snapshot.policy,
+      next_id : snapshot.next_id,
+      pending : snapshot.pending.into_iter()
+                                .map(|decision| (decision.id,decision))
+                                .collect(),
+      record : snapshot.record,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::estimators::EstimatorInit;
+
+    #[test]
+    fn test_report_out_of_order() {
+        let est = EstimatorInit::SampleAverageInit {nb_levers : 3};
+        let mut session = Session::new(&PolicyInit::EGreedyInit {nb_levers : 3,
+                                                                 expl_proba : 0.5.into(),
+                                                                 est : &est});
+        let first = session.choose();
+        let second = session.choose();
+        assert_ne!(first.id,second.id);
+        assert_eq!(session.nb_pending(),2);
+        assert_eq!(session.report(second.id,1.0),Ok(()));
+        assert_eq!(session.report(first.id,0.0),Ok(()));
+        assert_eq!(session.report(first.id,0.0),Err(SessionError::UnknownDecision(first.id)));
+        assert_eq!(session.nb_pending(),0);
+    }
+
+    #[test]
+    fn test_recorded_propensities() {
+        let est = EstimatorInit::SampleAverageInit {nb_levers : 2};
+        let mut egreedy = Session::new(&PolicyInit::EGreedyInit {nb_levers : 2,
+                                                                 expl_proba : 0.5.into(),
+                                                                 est : &est});
+        assert!(egreedy.choose().propensity.is_some());
+        let thompson = PolicyInit::ThompsonInit {nb_levers : 2, est : &est};
+        assert!(Session::new(&thompson).choose().propensity.is_none());
+        let mut session = Session::new(&thompson).record_probabilities(true);
+        session.choose();
+        let restored = Session::restore(session.snapshot());
+        assert!(restored.clone().choose().propensity.is_some());
+    }
+}
@@ -0,0 +1,208 @@
+use crate::experiments::Experiment;
+use crate::policies::PolicyEnum;
+use crate::session::Decision;
+
+use std::error::Error;
+use std::fmt;
+
+use serde::{Serialize,Deserialize};
+use serde::de::DeserializeOwned;
+
+pub(crate) mod floats;
+
+// Version of the snapshot format. It must be bumped whenever a
+// released format changes.
+pub const SNAPSHOT_VERSION : u32 = 1;
+
+#[derive(Clone,Debug,PartialEq)]
+pub enum SnapshotError {
+  // The data is not a well-formed snapshot.
+  Format(String),
+  // The snapshot was written with another version of the format.
+  Version(u32),
+  // The state could not be written.
+  Encoding(String),
+}
+
+impl fmt::Display for SnapshotError {
+
+  fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      SnapshotError::Format(msg) =>
+        write!(f, "malformed snapshot: {}", msg),
+      SnapshotError::Version(version) =>
+        write!(f, "snapshot version {} is not supported (expected {})",
+               version, SNAPSHOT_VERSION),
+      SnapshotError::Encoding(msg) =>
+        write!(f, "cannot write snapshot: {}", msg),
+    }
+  }
+}
+
+impl Error for SnapshotError {}
+
+// Complete state of a session: the learned state of its policy and
+// estimator, and the decisions still waiting for their reward.
+#[derive(Clone,Serialize,Deserialize)]
+pub struct SessionSnapshot {
+  version : u32,
+  pub(crate) policy : PolicyEnum,
+  pub(crate) next_id : u64,
+  pub(crate) pending : Vec<Decision>,
+  pub(crate) record : bool,
+}
+
+impl SessionSnapshot {
+
+  pub(crate) fn new(policy : PolicyEnum,
+                    next_id : u64,
+                    mut pending : Vec<Decision>,
+                    record : bool) -> Self {
+    pending.sort_by_key(|decision| decision.id);
+    SessionSnapshot {
+      version : SNAPSHOT_VERSION,
+      policy,
+      next_id,
+      pending,
+      record,
+    }
+  }
+
+  pub fn to_json(&self) -> Result<String,SnapshotError> {
+    to_json(self)
+  }
+
+  pub fn from_json(data : &str) -> Result<Self,SnapshotError> {
+    from_versioned_json(data)
+  }
+}
+
+// Complete state of a simulation: the policy, the problem, the steps
+// made so far and the rewards not given to the policy yet. The random
+// generator is not part of it, and must be saved by the caller.
+#[derive(Clone,Serialize,Deserialize)]
+pub struct ExperimentSnapshot {
+  version : u32,
+  pub(crate) experiment : Experiment,
+}
+
+impl ExperimentSnapshot {
+
+  pub(crate) fn new(experiment : Experiment) -> Self {
+    ExperimentSnapshot {
+      version : SNAPSHOT_VERSION,
+      experiment,
+    }
+  }
+
+  pub fn to_json(&self) -> Result<String,SnapshotError> {
+    to_json(self)
+  }
+
+  pub fn from_json(data : &str) -> Result<Self,SnapshotError> {
+    from_versioned_json(data)
+  }
+}
+
+fn to_json<T : Serialize>(snapshot : &T) -> Result<String,SnapshotError> {
+  serde_json::to_string(snapshot).map_err(|e| SnapshotError::Encoding(e.to_string()))
+}
+
+// Check the version of the snapshot before deserializing the rest,
+// so that an old snapshot is reported as such and not as garbage.
+fn from_versioned_json<T : DeserializeOwned>(data : &str) -> Result<T,SnapshotError> {
+  #[derive(Deserialize)]
+  struct Header {
+    version : u32,
+  }
+
+  let header : Header =
+    serde_json::from_str(data).map_err(|e| SnapshotError::Format(e.to_string()))?;
+  if header.version != SNAPSHOT_VERSION {
+    return Err(SnapshotError::Version(header.version));
+  }
+  serde_json::from_str(data).map_err(|e| SnapshotError::Format(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::estimators::{EstimatorInit,InitialValues};
+    use crate::experiments::ClockInit;
+    use crate::policies::PolicyInit;
+    use crate::problems::BanditInit;
+    use crate::session::Session;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_session_round_trip() {
+        let est = EstimatorInit::ConstantStepInit {nb_levers : 2, step : 0.5};
+        let mut session = Session::new(&PolicyInit::EGreedyInit {nb_levers : 2,
+                                                                 expl_proba : 0.0.into(),
+                                                                 est : &est});
+        let first = session.choose();
+        session.report(first.id,1.0).unwrap();
+        let pending = session.choose();
+
+        let json = session.snapshot().to_json().unwrap();
+        let mut restored = Session::restore(SessionSnapshot::from_json(&json).unwrap());
+        assert_eq!(restored.nb_pending(),1);
+        assert_eq!(restored.snapshot().to_json().unwrap(),json);
+        assert_eq!(restored.report(pending.id,0.0),Ok(()));
+        assert!(restored.choose().id > pending.id);
+    }
+
+    #[test]
+    fn test_version_mismatch() {
+        assert_eq!(SessionSnapshot::from_json("{\"version\":0}").err(),
+                   Some(SnapshotError::Version(0)));
+    }
+
+    #[test]
+    fn test_non_finite_state() {
+        let est = EstimatorInit::PriorSampleAverageInit {nb_levers : 2,
+                                                         init : InitialValues::PerLever(vec![f64::INFINITY,
+                                                                                             f64::NEG_INFINITY]),
+                                                         pseudo_counts : 1.0};
+        let session = Session::new(&PolicyInit::EGreedyInit {nb_levers : 2,
+                                                             expl_proba : 0.0.into(),
+                                                             est : &est});
+        let json = session.snapshot().to_json().unwrap();
+        assert!(json.contains("\"inf\"") && json.contains("\"-inf\""));
+        let restored = Session::restore(SessionSnapshot::from_json(&json).unwrap());
+        assert_eq!(restored.estimates(),vec![f64::INFINITY,f64::NEG_INFINITY]);
+        assert!(SessionSnapshot::from_json(&json.replace("\"inf\"","\"infinity\"")).is_err());
+    }
+
+    #[test]
+    fn test_experiment_resume() {
+        let est = EstimatorInit::SampleAverageInit {nb_levers : 3};
+        let policy = PolicyInit::EGreedyInit {nb_levers : 3, expl_proba : 0.1.into(), est : &est};
+        let problem = BanditInit::NonStationaryInit {nb_levers : 3,
+                                                     init_vals : (0.0,1.0),
+                                                     walk : (0.0,0.1)};
+        let clock = ClockInit { pulls_per_tick : 1, ticks_per_round : 1, batch_size : 3 };
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut whole = Experiment::from_init(&policy,&problem,clock,100,&mut rng);
+        whole.run(100,&mut rng);
+        let whole = whole.finish();
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut first = Experiment::from_init(&policy,&problem,clock,100,&mut rng);
+        // Stop in the middle of a batch, with rewards still pending.
+        first.run(50,&mut rng);
+        let json = first.snapshot().to_json().unwrap();
+        drop(first);
+        let mut resumed = Experiment::restore(ExperimentSnapshot::from_json(&json).unwrap());
+        assert_eq!(resumed.results().len(),50);
+        resumed.run(100,&mut rng);
+        let resumed = resumed.finish();
+
+        assert_eq!(resumed.len(),whole.len());
+        for (x,y) in resumed.iter().zip(whole.iter()) {
+            assert_eq!((x.lever,x.reward,x.alarm),(y.lever,y.reward,y.alarm));
+        }
+    }
+}
@@ -0,0 +1,61 @@
+// JSON has no infinite or NaN numbers, and serde_json writes them as
+// null, which cannot be read back as a float. The fields which can
+// legitimately hold them, like infinite initial values, are written
+// with these helpers as the strings "inf", "-inf" and "NaN" instead.
+
+use serde::{Serialize,Serializer,Deserialize,Deserializer};
+use serde::de::{Error,Unexpected};
+
+#[derive(Serialize,Deserialize)]
+#[serde(untagged)]
+enum Float {
+  Number(f64),
+  Name(String),
+}
+
+fn to_float(value : f64) -> Float {
+  if value.is_finite() {
+    Float::Number(value)
+  } else if value.is_nan() {
+    Float::Name("NaN".to_string())
+  } else if value > 0.0 {
+    Float::Name("inf".to_string())
+  } else {
+    Float::Name("-inf".to_string())
+  }
+}
+
+fn from_float<E : Error>(float : Float) -> Result<f64,E> {
+  match float {
+    Float::Number(value) => Ok(value),
+    Float::Name(name) => match name.as_str() {
+      "inf" => Ok(f64::INFINITY),
+      "-inf" => Ok(f64::NEG_INFINITY),
+      "NaN" => Ok(f64::NAN),
+      _ => Err(E::invalid_value(Unexpected::Str(&name), &"a number, \"inf\", \"-inf\" or \"NaN\"")),
+    },
+  }
+}
+
+pub(crate) fn serialize<S : Serializer>(value : &f64, serializer : S) -> Result<S::Ok,S::Error> {
+  to_float(*value).serialize(serializer)
+}
+
+pub(crate) fn deserialize<'de,D : Deserializer<'de>>(deserializer : D) -> Result<f64,D::Error> {
+  from_float(Float::deserialize(deserializer)?)
+}
+
+// The same for a vector of floats.
+pub(crate) mod vec {
+  use super::*;
+
+  pub(crate) fn serialize<S : Serializer>(values : &[f64], serializer : S) -> Result<S::Ok,S::Error> {
+    serializer.collect_seq(values.iter().map(|value| to_float(*value)))
+  }
+
+  pub(crate) fn deserialize<'de,D : Deserializer<'de>>(deserializer : D) -> Result<Vec<f64>,D::Error> {
+    Vec::<Float>::deserialize(deserializer)?.into_iter()
+                                            .map(from_float)
+                                            .collect()
+  }
+}
@@ -0,0 +1,89 @@
+#![cfg(feature = "server")]
+
+use bandit_rs::Server;
+
+use std::io::{Read,Write};
+use std::net::{SocketAddr,TcpStream};
+use std::thread;
+
+const NB_LEVERS:usize = 3;
+
+fn request(addr : SocketAddr, method : &str, path : &str, body : &str) -> (u16,serde_json::Value) {
+  let mut stream = TcpStream::connect(addr).unwrap();
+  write!(stream,
+         "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+         method, path, body.len(), body).unwrap();
+  let mut response = String::new();
+  stream.read_to_string(&mut response).unwrap();
+  let status = response[9..12].parse().unwrap();
+  let body = &response[response.find("\r\n\r\n").unwrap()+4..];
+  (status, serde_json::from_str(body).unwrap())
+}
+
+#[test]
+fn session_over_http() {
+  let server = Server::bind("127.0.0.1:0").unwrap();
+  let addr = server.local_addr();
+  thread::spawn(move || server.run());
+
+  let config = format!("{{\"EGreedyInit\":{{\"nb_levers\":{},\"expl_proba\":0.1,\
+                        \"est\":{{\"SampleAverageInit\":{{\"nb_levers\":{}}}}}}}}}",
+                       NB_LEVERS, NB_LEVERS);
+  assert_eq!(request(addr,"POST","/sessions/ab",&config).0, 201);
+  assert_eq!(request(addr,"POST","/sessions/ab",&config).0, 409);
+
+  let (status,decision) = request(addr,"POST","/sessions/ab/decisions","");
+  assert_eq!(status, 200);
+  let lever = decision["lever"].as_u64().unwrap() as usize;
+  assert!(lever < NB_LEVERS);
+
+  let reward = format!("{{\"id\":{},\"reward\":2.0}}", decision["id"]);
+  assert_eq!(request(addr,"POST","/sessions/ab/rewards",&reward).0, 200);
+  assert_eq!(request(addr,"POST","/sessions/ab/rewards",&reward).0, 404);
+
+  let (status,estimates) = request(addr,"GET","/sessions/ab/estimates","");
+  assert_eq!(status, 200);
+  assert_eq!(estimates["estimates"][lever], 2.0);
+
+  assert_eq!(request(addr,"DELETE","/sessions/ab","").0, 200);
+  assert_eq!(request(addr,"GET","/sessions/ab/estimates","").0, 404);
+}
+
+#[test]
+fn invalid_configs() {
+  let server = Server::bind("127.0.0.1:0").unwrap();
+  let addr = server.local_addr();
+  thread::spawn(move || server.run());
+
+  let sample_average = "{\"SampleAverageInit\":{\"nb_levers\":3}}";
+  let configs = [
+    // No lever to choose from.
+    "{\"ThompsonInit\":{\"nb_levers\":0,\"est\":{\"SampleAverageInit\":{\"nb_levers\":0}}}}".to_string(),
+    // Exploration probability above 1.
+    format!("{{\"EGreedyInit\":{{\"nb_levers\":3,\"expl_proba\":{{\"Constant\":1.5}},\"est\":{}}}}}",
+            sample_average),
+    // Estimator with fewer levers than the policy.
+    "{\"UCBTunedInit\":{\"nb_levers\":3,\"est\":{\"SampleAverageInit\":{\"nb_levers\":2}}}}".to_string(),
+    // Degenerate priors.
+    "{\"ThompsonInit\":{\"nb_levers\":3,\
+      \"est\":{\"BetaBernoulliInit\":{\"nb_levers\":3,\"alpha\":0.0,\"beta\":1.0}}}}".to_string(),
+    "{\"ThompsonInit\":{\"nb_levers\":3,\
+      \"est\":{\"GammaPoissonInit\":{\"nb_levers\":3,\"shape\":1.0,\"rate\":0.0}}}}".to_string(),
+    // Change detector which never warms up or always alarms.
+    format!("{{\"CusumUCBInit\":{{\"nb_levers\":3,\"step\":1.0,\"expl_proba\":0.1,\"warmup\":10,\
+             \"drift\":0.0,\"threshold\":1.0,\"est\":{}}}}}", sample_average),
+    // Step sizes which make every estimate NaN.
+    "{\"UCBInit\":{\"nb_levers\":3,\"step\":1.0,\"est\":{\"ScheduledStepInit\":{\"nb_levers\":3,\
+      \"steps\":{\"Harmonic\":{\"scale\":0.0}},\"init\":{\"Uniform\":0.0}}}}}".to_string(),
+  ];
+  for (nb,config) in configs.iter().enumerate() {
+    let (status,body) = request(addr,"POST",&format!("/sessions/bad{}",nb),config);
+    assert_eq!(status, 400, "{}", config);
+    assert!(body["error"].is_string());
+    assert_eq!(request(addr,"POST",&format!("/sessions/bad{}/decisions",nb),"").0, 404);
+  }
+
+  let config = format!("{{\"ThompsonInit\":{{\"nb_levers\":3,\"est\":{}}}}}", sample_average);
+  assert_eq!(request(addr,"POST","/sessions/good",&config).0, 201);
+  assert_eq!(request(addr,"POST","/sessions/good/decisions","").0, 200);
+}
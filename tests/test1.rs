@@ -20,13 +20,13 @@ fn experiment() {
   let est = EstimatorInit::SampleAverageInit {nb_levers : NB_LEVERS};
 
   let policies = [ PolicyInit::EGreedyInit {nb_levers : NB_LEVERS,
-                                            expl_proba : EPS,
+                                            expl_proba : EPS.into(),
                                             est : &est},
                    PolicyInit::EGreedyInit {nb_levers : NB_LEVERS,
-                                            expl_proba : EPS2,
+                                            expl_proba : EPS2.into(),
                                             est : &est},
                    PolicyInit::EGreedyInit {nb_levers : NB_LEVERS,
-                                            expl_proba : EPS3,
+                                            expl_proba : EPS3.into(),
                                             est : &est}
                  ];
 
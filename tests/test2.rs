@@ -9,6 +9,7 @@ const EPS:f64 = 0.1;
 const NAME: &str = "e = 0.1, sample average";
 const ALPHA:f64 = 0.1;
 const NAME2: &str = "alpha = 0.1, constant step";
+const NAME3: &str = "Thompson sampling, Kalman filter";
 
 #[test]
 fn experiment() {
@@ -21,12 +22,19 @@ fn experiment() {
   let est1 = EstimatorInit::SampleAverageInit {nb_levers : NB_LEVERS};
   let est2 = EstimatorInit::ConstantStepInit {nb_levers : NB_LEVERS,
                                               step : ALPHA};
+  let est3 = EstimatorInit::KalmanInit {nb_levers : NB_LEVERS,
+                                        init_mean : GAUSS.0,
+                                        init_var : 0.0,
+                                        walk_var : WALK.1*WALK.1,
+                                        obs_var : GAUSS.1*GAUSS.1};
   let policies = [ PolicyInit::EGreedyInit {nb_levers : NB_LEVERS,
-                                            expl_proba : EPS,
+                                            expl_proba : EPS.into(),
                                             est : &est1},
                    PolicyInit::EGreedyInit {nb_levers : NB_LEVERS,
-                                            expl_proba : EPS,
-                                            est : &est2}
+                                            expl_proba : EPS.into(),
+                                            est : &est2},
+                   PolicyInit::ThompsonInit {nb_levers : NB_LEVERS,
+                                             est : &est3}
                  ];
 
   let results : Vec<Vec<f64>> =
@@ -37,7 +45,7 @@ fn experiment() {
                                   NB_TRIES,
                                   LEN_EXP);
 
-  let names = [ NAME, NAME2 ];
+  let names = [ NAME, NAME2, NAME3 ];
 
   bandit_rs::plot_results(&results,&names, LEN_EXP);
 }
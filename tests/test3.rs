@@ -13,8 +13,8 @@ fn experiment() {
 
   let est = EstimatorInit::SampleAverageInit {nb_levers : NB_LEVERS};
   let policy = PolicyInit::EGreedyInit {nb_levers : NB_LEVERS,
-                                        expl_proba : (2.0_f64).powi(-7),
+                                        expl_proba : (2.0_f64).powi(-7).into(),
                                         est : &est};
 
-  bandit_rs::run_parameter_study(&policy,&problem, LEN_EXP, 0..6);
+  bandit_rs::run_parameter_study(&policy,&problem, LEN_EXP, 0..6).unwrap();
 }
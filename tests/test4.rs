@@ -0,0 +1,48 @@
+use bandit_rs::{BanditInit,EstimatorInit,InitialValues,PolicyInit};
+
+const NB_LEVERS:usize = 10;
+const NB_TRIES:usize = 2000;
+const LEN_EXP:usize = 1000;
+const GAUSS:(f64,f64) = (0.0,1.0);
+const ALPHA:f64 = 0.1;
+const OPTIMISTIC:f64 = 5.0;
+const NAME: &str = "Q1 = 5, e = 0, constant step";
+const EPS2:f64 = 0.1;
+const NAME2: &str = "Q1 = 0, e = 0.1, constant step";
+const NAME3: &str = "Q1 = 5, e = 0, unbiased constant step";
+
+#[test]
+fn experiment() {
+
+  let problem : BanditInit =
+    BanditInit::StationaryInit {nb_levers : NB_LEVERS,
+                                init_vals : GAUSS};
+  let est1 = EstimatorInit::PriorConstantStepInit {nb_levers : NB_LEVERS,
+                                                   step : ALPHA,
+                                                   init : InitialValues::Uniform(OPTIMISTIC)};
+  let est2 = EstimatorInit::ConstantStepInit {nb_levers : NB_LEVERS,
+                                              step : ALPHA};
+  let est3 = EstimatorInit::UnbiasedConstantStepInit {nb_levers : NB_LEVERS,
+                                                      step : ALPHA,
+                                                      init : InitialValues::Uniform(OPTIMISTIC)};
+
+  let policies = [ PolicyInit::EGreedyInit {nb_levers : NB_LEVERS,
+                                            expl_proba : 0.0.into(),
+                                            est : &est1},
+                   PolicyInit::EGreedyInit {nb_levers : NB_LEVERS,
+                                            expl_proba : EPS2.into(),
+                                            est : &est2},
+                   PolicyInit::EGreedyInit {nb_levers : NB_LEVERS,
+                                            expl_proba : 0.0.into(),
+                                            est : &est3}
+                 ];
+
+  let results : Vec<Vec<f64>> =
+    bandit_rs::optimal_percentage(bandit_rs::run_experiments(&policies,problem,NB_TRIES,LEN_EXP),
+                                  NB_TRIES,
+                                  LEN_EXP);
+
+  let names = [NAME,NAME2,NAME3];
+
+  bandit_rs::plot_results(&results[..], &names, LEN_EXP);
+}